@@ -0,0 +1,192 @@
+use std::path::Path;
+use std::time::Duration;
+
+pub const SYSFS: &str = "/sys/class/power_supply";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Charging,
+    Discharging,
+    Full,
+    /// Plugged in but holding, e.g. at a charge limit
+    NotCharging,
+    Unknown,
+}
+
+impl State {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            State::Charging    => "charging",
+            State::Discharging => "discharging",
+            State::Full        => "full",
+            State::NotCharging => "not charging",
+            State::Unknown     => "unknown",
+        }
+    }
+}
+
+pub struct Battery {
+    pub percent: u8,
+    pub state: State,
+    /// Until empty when discharging, until full when charging
+    pub remaining: Option<Duration>,
+}
+
+// One supply's readings. sysfs reports either energy (µWh, µW) or charge
+// (µAh, µA); the pairs are interchangeable for percentages and durations.
+struct Supply {
+    state: State,
+    capacity: Option<f64>,
+    now: Option<f64>,
+    full: Option<f64>,
+    rate: Option<f64>,
+}
+
+/// Combine every battery under `dir` (or just `only`, e.g. "BAT0").
+pub fn read(dir: &Path, only: Option<&str>) -> Option<Battery> {
+    let mut supplies = Vec::new();
+    for e in std::fs::read_dir(dir).ok()?.flatten() {
+        let name = e.file_name().to_string_lossy().to_string();
+        if only.is_some_and(|o| o != name) {
+            continue;
+        }
+        let p = e.path();
+        if attr(&p, "type").as_deref() != Some("Battery") || attr(&p, "present").as_deref() == Some("0") {
+            continue;
+        }
+        supplies.push(read_supply(&p));
+    }
+    if supplies.is_empty() {
+        return None;
+    }
+
+    let sum = |f: fn(&Supply) -> Option<f64>| -> Option<f64> {
+        supplies.iter().map(f).sum::<Option<f64>>()
+    };
+    let now  = sum(|s| s.now);
+    let full = sum(|s| s.full);
+    let rate = sum(|s| s.rate).filter(|&r| r > 0.0);
+
+    let percent = match (now, full) {
+        (Some(n), Some(f)) if f > 0.0 => n / f * 100.0,
+        _ => {
+            let caps: Vec<f64> = supplies.iter().filter_map(|s| s.capacity).collect();
+            if caps.is_empty() {
+                return None;
+            }
+            caps.iter().sum::<f64>() / caps.len() as f64
+        }
+    };
+
+    // any battery charging wins, then discharging; all full means full
+    let states: Vec<State> = supplies.iter().map(|s| s.state).collect();
+    let state = [State::Charging, State::Discharging, State::NotCharging]
+        .into_iter()
+        .find(|s| states.contains(s))
+        .unwrap_or(if states.iter().all(|&s| s == State::Full) { State::Full } else { State::Unknown });
+
+    let hours = match (state, now, full, rate) {
+        (State::Discharging, Some(n), _, Some(r)) => Some(n / r),
+        (State::Charging, Some(n), Some(f), Some(r)) => Some((f - n).max(0.0) / r),
+        _ => None,
+    };
+
+    Some(Battery {
+        percent: percent.round().clamp(0.0, 100.0) as u8,
+        state,
+        remaining: hours.map(|h| Duration::from_secs_f64(h * 3600.0)),
+    })
+}
+
+fn read_supply(p: &Path) -> Supply {
+    let num = |f: &str| attr(p, f).and_then(|v| v.parse::<f64>().ok());
+    let state = match attr(p, "status").as_deref() {
+        Some("Charging")     => State::Charging,
+        Some("Discharging")  => State::Discharging,
+        Some("Full")         => State::Full,
+        Some("Not charging") => State::NotCharging,
+        _                    => State::Unknown,
+    };
+    Supply {
+        state,
+        capacity: num("capacity"),
+        now:  num("energy_now").or_else(|| num("charge_now")),
+        full: num("energy_full").or_else(|| num("charge_full")),
+        // some drivers report a negative current while discharging
+        rate: num("power_now").or_else(|| num("current_now")).map(f64::abs),
+    }
+}
+
+fn attr(dir: &Path, name: &str) -> Option<String> {
+    Some(std::fs::read_to_string(dir.join(name)).ok()?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn supply(name: &str, attrs: &[(&str, &str)]) -> Vec<(String, String)> {
+        attrs.iter().map(|(k, v)| (format!("{name}/{k}"), v.to_string())).collect()
+    }
+
+    fn fixture(name: &str, supplies: &[Vec<(String, String)>]) -> Fixture {
+        let files: Vec<(&str, &str)> = supplies.iter().flatten().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        Fixture::new(name, &files)
+    }
+
+    #[test]
+    fn combines_batteries_and_charging_wins() {
+        let f = fixture("battery-combine", &[
+            supply("BAT0", &[("type", "Battery"), ("status", "Discharging"),
+                ("energy_now", "30000000"), ("energy_full", "50000000"), ("power_now", "10000000")]),
+            supply("BAT1", &[("type", "Battery"), ("status", "Charging"),
+                ("energy_now", "20000000"), ("energy_full", "50000000"), ("power_now", "0")]),
+            supply("AC", &[("type", "Mains"), ("online", "1")]),
+        ]);
+        let b = read(f.path(), None).unwrap();
+        assert_eq!(b.percent, 50);
+        assert_eq!(b.state, State::Charging);
+        assert_eq!(b.remaining, Some(Duration::from_secs(5 * 3600)));
+
+        let b = read(f.path(), Some("BAT0")).unwrap();
+        assert_eq!(b.percent, 60);
+        assert_eq!(b.state, State::Discharging);
+        assert_eq!(b.remaining, Some(Duration::from_secs(3 * 3600)));
+    }
+
+    #[test]
+    fn full_only_when_every_battery_is() {
+        let full = |n| supply(n, &[("type", "Battery"), ("status", "Full"), ("capacity", "100")]);
+        let f = fixture("battery-full", &[full("BAT0"), full("BAT1")]);
+        assert_eq!(read(f.path(), None).unwrap().state, State::Full);
+
+        let f = fixture("battery-unknown", &[
+            full("BAT0"),
+            supply("BAT1", &[("type", "Battery"), ("status", "Unknown"), ("capacity", "80")]),
+        ]);
+        let b = read(f.path(), None).unwrap();
+        assert_eq!(b.state, State::Unknown);
+        // no energy or charge readings: capacities are averaged
+        assert_eq!(b.percent, 90);
+        assert_eq!(b.remaining, None);
+    }
+
+    #[test]
+    fn charge_readings_and_absent_batteries() {
+        let f = fixture("battery-charge", &[
+            supply("BAT0", &[("type", "Battery"), ("status", "Discharging"),
+                ("charge_now", "1500000"), ("charge_full", "3000000"), ("current_now", "-750000")]),
+            supply("BAT1", &[("type", "Battery"), ("present", "0"), ("status", "Charging")]),
+        ]);
+        let b = read(f.path(), None).unwrap();
+        assert_eq!((b.percent, b.state), (50, State::Discharging));
+        assert_eq!(b.remaining, Some(Duration::from_secs(2 * 3600)));
+    }
+
+    #[test]
+    fn no_batteries() {
+        let f = fixture("battery-none", &[supply("AC", &[("type", "Mains")])]);
+        assert!(read(f.path(), None).is_none());
+    }
+}
@@ -0,0 +1,35 @@
+// Small values that outlive a single run, one file each under
+// $XDG_CACHE_HOME/tmuxstar. Losing them is harmless.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+pub fn dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("tmuxstar"))
+}
+
+/// File-name-safe key for anything hashable, e.g. a path plus arguments.
+pub fn key(v: impl Hash) -> String {
+    let mut h = DefaultHasher::new();
+    v.hash(&mut h);
+    format!("{:016x}", h.finish())
+}
+
+pub fn load(name: &str) -> Option<String> {
+    std::fs::read_to_string(dir()?.join(name)).ok()
+}
+
+pub fn store(name: &str, value: &str) {
+    let Some(dir) = dir() else { return; };
+    let _ = std::fs::create_dir_all(&dir);
+    // write + rename so a concurrent reader never sees half a value
+    let tmp = dir.join(format!("{name}.{}", std::process::id()));
+    if std::fs::write(&tmp, value).is_ok() {
+        let _ = std::fs::rename(&tmp, dir.join(name));
+    }
+}
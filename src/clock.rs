@@ -0,0 +1,202 @@
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+
+/// A labelled time zone for the time segment.
+#[derive(Clone, Debug)]
+pub struct Zone {
+    pub label: String,
+    pub tz: Option<Tz>, // None is the local zone
+}
+
+impl Zone {
+    pub fn local() -> Zone {
+        Zone { label: String::new(), tz: None }
+    }
+
+    pub fn format(&self, now: DateTime<Utc>, format: &str) -> String {
+        match self.tz {
+            Some(tz) => now.with_timezone(&tz).format(format).to_string(),
+            None => now.with_timezone(&Local).format(format).to_string(),
+        }
+    }
+}
+
+/// `LABEL=Area/City`, `Area/City` (labelled `City`) or `local`.
+pub fn parse_zone(s: &str) -> Result<Zone, String> {
+    let (label, name) = match s.split_once('=') {
+        Some((l, n)) => (Some(l.to_string()), n),
+        None => (None, s),
+    };
+    let tz = if name.eq_ignore_ascii_case("local") {
+        None
+    } else {
+        Some(name.parse::<Tz>().map_err(|_| format!("unknown time zone `{name}`"))?)
+    };
+    let label = label.unwrap_or_else(|| {
+        let city = name.rsplit('/').next().unwrap_or(name);
+        city.replace('_', " ")
+    });
+    Ok(Zone { label, tz })
+}
+
+/// `LABEL=value`, for per-zone overrides.
+pub fn parse_override(s: &str) -> Result<(String, String), String> {
+    let (l, v) = s.split_once('=').ok_or_else(|| format!("expected LABEL=VALUE, got `{s}`"))?;
+    Ok((l.to_string(), v.to_string()))
+}
+
+/// The override for `label`, last one wins like repeated flags elsewhere.
+pub fn lookup<'a>(overrides: &'a [(String, String)], label: &str) -> Option<&'a str> {
+    overrides.iter().rev().find(|(l, _)| l == label).map(|(_, v)| v.as_str())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Day {
+    fn weekday(self) -> Weekday {
+        match self {
+            Day::Mon => Weekday::Mon,
+            Day::Tue => Weekday::Tue,
+            Day::Wed => Weekday::Wed,
+            Day::Thu => Weekday::Thu,
+            Day::Fri => Weekday::Fri,
+            Day::Sat => Weekday::Sat,
+            Day::Sun => Weekday::Sun,
+        }
+    }
+}
+
+/// `HH:MM`.
+pub fn parse_hm(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| format!("expected HH:MM, got `{s}`"))
+}
+
+/// `HH:MM-HH:MM`; an end before the start runs past midnight.
+pub fn parse_span(s: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let (a, b) = s.split_once('-').ok_or_else(|| format!("expected HH:MM-HH:MM, got `{s}`"))?;
+    Ok((parse_hm(a)?, parse_hm(b)?))
+}
+
+/// Where `now` falls in the working week.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hours {
+    Work,
+    Off,
+    DayOff,
+}
+
+impl Hours {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Hours::Work   => "work",
+            Hours::Off    => "off",
+            Hours::DayOff => "day-off",
+        }
+    }
+}
+
+pub fn hours<Z: TimeZone>(now: DateTime<Z>, span: (NaiveTime, NaiveTime), days: &[Day]) -> Hours {
+    let (start, end) = span;
+    let t = now.time();
+    let (inside, day) = if start <= end {
+        (t >= start && t < end, now.weekday())
+    } else if t >= start {
+        (true, now.weekday())
+    } else if t < end {
+        // the small hours belong to the shift that started yesterday
+        (true, now.weekday().pred())
+    } else {
+        (false, now.weekday())
+    };
+    if !days.iter().any(|d| d.weekday() == day) {
+        Hours::DayOff
+    } else if inside {
+        Hours::Work
+    } else {
+        Hours::Off
+    }
+}
+
+/// The first moment after `now` when the local clock reads `t`.
+pub fn next_at<Z: TimeZone>(now: DateTime<Z>, t: NaiveTime) -> Option<DateTime<Z>> {
+    let mut date = now.date_naive();
+    for _ in 0..2 {
+        if let Some(at) = now.timezone().from_local_datetime(&date.and_time(t)).earliest()
+            && at > now
+        {
+            return Some(at);
+        }
+        date = date.succ_opt()?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEEKDAYS: &[Day] = &[Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri];
+
+    // 2026-10-19 is a Monday
+    fn at(day: u32, h: u32, m: u32) -> DateTime<Tz> {
+        chrono_tz::Europe::London.with_ymd_and_hms(2026, 10, day, h, m, 0).unwrap()
+    }
+
+    fn hm(s: &str) -> NaiveTime {
+        parse_hm(s).unwrap()
+    }
+
+    #[test]
+    fn same_day_span() {
+        let span = (hm("09:00"), hm("17:30"));
+        assert_eq!(hours(at(19, 8, 59), span, WEEKDAYS), Hours::Off);
+        assert_eq!(hours(at(19, 9, 0), span, WEEKDAYS), Hours::Work);
+        assert_eq!(hours(at(19, 17, 30), span, WEEKDAYS), Hours::Off);
+        assert_eq!(hours(at(18, 12, 0), span, WEEKDAYS), Hours::DayOff);
+    }
+
+    #[test]
+    fn overnight_span() {
+        let span = (hm("22:00"), hm("06:00"));
+        // Monday night's shift runs into Tuesday
+        assert_eq!(hours(at(19, 23, 0), span, WEEKDAYS), Hours::Work);
+        assert_eq!(hours(at(20, 5, 59), span, WEEKDAYS), Hours::Work);
+        assert_eq!(hours(at(20, 6, 0), span, WEEKDAYS), Hours::Off);
+        // Monday daytime is between shifts, not part of Sunday
+        assert_eq!(hours(at(19, 10, 0), span, WEEKDAYS), Hours::Off);
+        // Saturday's small hours finish Friday's shift; Sunday's have none
+        assert_eq!(hours(at(24, 3, 0), span, WEEKDAYS), Hours::Work);
+        assert_eq!(hours(at(25, 3, 0), span, WEEKDAYS), Hours::DayOff);
+        assert_eq!(hours(at(24, 23, 0), span, WEEKDAYS), Hours::DayOff);
+    }
+
+    #[test]
+    fn next_at_rolls_over_to_tomorrow() {
+        assert_eq!(next_at(at(19, 9, 0), hm("17:30")), Some(at(19, 17, 30)));
+        assert_eq!(next_at(at(19, 17, 30), hm("17:30")), Some(at(20, 17, 30)));
+        assert_eq!(next_at(at(19, 18, 0), hm("08:00")), Some(at(20, 8, 0)));
+    }
+
+    #[test]
+    fn next_at_across_dst_changes() {
+        let london = chrono_tz::Europe::London;
+        // 01:30 doesn't happen on 2027-03-28, when clocks jump from 01:00 to 02:00
+        let now = london.with_ymd_and_hms(2027, 3, 28, 0, 30, 0).unwrap();
+        let want = london.with_ymd_and_hms(2027, 3, 29, 1, 30, 0).unwrap();
+        assert_eq!(next_at(now, hm("01:30")), Some(want));
+        // and 01:30 happens twice on 2026-10-25: the first one counts
+        let now = london.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap();
+        let got = next_at(now, hm("01:30")).unwrap();
+        assert_eq!(got - now, chrono::Duration::hours(1));
+    }
+}
@@ -0,0 +1,216 @@
+// Config file support.
+//
+// The file mirrors the CLI: one table per subcommand whose keys are flag
+// names, plus named profiles layered on top:
+//
+//   [git]
+//   icon = " "
+//   counts = ["staged", "untracked"]
+//
+//   [profile.work.git]
+//   format = "{project}:{branch}"
+//
+// Values are turned back into `--flag=value` arguments and appended to the
+// command line, but only for flags the user did not pass. Precedence is
+// therefore: CLI > profile > file defaults > built-in defaults.
+
+use clap::{ArgAction, ArgMatches, Command};
+use clap::parser::ValueSource;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+pub fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("tmuxstar").join("config.toml"))
+}
+
+pub fn load(path: Option<&str>) -> Result<Table, String> {
+    let (path, explicit) = match path {
+        Some(p) => (PathBuf::from(p), true),
+        None => match default_path() {
+            Some(p) => (p, false),
+            None => return Ok(Table::new()),
+        },
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if !explicit && e.kind() == std::io::ErrorKind::NotFound => return Ok(Table::new()),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    text.parse::<Table>().map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// `argv` with config-file values filled in for every flag of the chosen
+/// subcommand that was left at its default.
+pub fn merged_args(cmd: Command, mut argv: Vec<OsString>) -> Vec<OsString> {
+    // let clap report usage errors and --help against the original argv
+    let Ok(matches) = cmd.clone().try_get_matches_from(&argv) else { return argv; };
+
+    let file = match load(matches.get_one::<String>("config").map(String::as_str)) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("tmuxstar: {e}");
+            std::process::exit(2);
+        }
+    };
+    if file.is_empty() {
+        return argv;
+    }
+
+    // walk down to the leaf subcommand, remembering its name path
+    let mut names = Vec::new();
+    let (mut cmd, mut m) = (cmd, &matches);
+    while let Some((name, sub)) = m.subcommand() {
+        names.push(name.to_string());
+        let Some(c) = cmd.find_subcommand(name).cloned() else { return argv; };
+        cmd = c;
+        m = sub;
+    }
+    if names.is_empty() {
+        return argv;
+    }
+
+    let mut layers = vec![section(&file, &names)];
+    if let Some(p) = matches.get_one::<String>("profile") {
+        match file.get("profile").and_then(Value::as_table).and_then(|t| t.get(p)) {
+            Some(Value::Table(t)) => layers.push(section(t, &names)),
+            _ => eprintln!("tmuxstar: no profile named `{p}` in config"),
+        }
+    }
+
+    // later layers win, so apply the profile first and skip what it set
+    let mut seen = Vec::new();
+    for table in layers.into_iter().rev().flatten() {
+        for (key, val) in table {
+            let id = key.replace('-', "_");
+            if seen.contains(&id) {
+                continue;
+            }
+            seen.push(id.clone());
+            append(&mut argv, &cmd, m, &id, val);
+        }
+    }
+    argv
+}
+
+fn section<'a>(table: &'a Table, names: &[String]) -> Option<&'a Table> {
+    let mut t = table;
+    for n in names {
+        t = t.get(n)?.as_table()?;
+    }
+    Some(t)
+}
+
+fn append(argv: &mut Vec<OsString>, cmd: &Command, m: &ArgMatches, id: &str, val: &Value) {
+    // keys may name the flag (`path`) or the field behind it (`paths`)
+    let Some(arg) = cmd.get_arguments().find(|a| {
+        a.get_id() == id || a.get_long().is_some_and(|l| l.replace('-', "_") == id)
+    }) else {
+        // nested tables are sub-subcommand sections, not flags
+        if !val.is_table() {
+            eprintln!("tmuxstar: unknown config key `{id}` for `{}`", cmd.get_name());
+        }
+        return;
+    };
+    if m.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+        return;
+    }
+    let Some(long) = arg.get_long() else { return; };
+
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+        if val.as_bool() == Some(true) {
+            argv.push(format!("--{long}").into());
+        }
+        return;
+    }
+    let values: Vec<String> = match val {
+        Value::Array(items) => items.iter().map(scalar).collect(),
+        v => vec![scalar(v)],
+    };
+    if arg.get_value_delimiter().is_some() {
+        argv.push(format!("--{long}={}", values.join(",")).into());
+    } else {
+        for v in values {
+            argv.push(format!("--{long}={v}").into());
+        }
+    }
+}
+
+fn scalar(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use clap::Arg;
+
+    const FILE: &str = r#"
+[git]
+icon = "file"
+format = "file"
+counts = ["staged", "untracked"]
+stale = true
+
+[profile.work.git]
+format = "work"
+"#;
+
+    fn cmd() -> Command {
+        let flag = |name: &'static str| Arg::new(name).long(name);
+        Command::new("tmuxstar")
+            .arg(flag("config").global(true))
+            .arg(flag("profile").global(true))
+            .subcommand(Command::new("git")
+                .arg(flag("icon").default_value(""))
+                .arg(flag("format"))
+                .arg(flag("counts").value_delimiter(','))
+                .arg(flag("stale").action(ArgAction::SetTrue)))
+    }
+
+    // What merged_args appended to `args`.
+    fn added(f: &Fixture, args: &[&str]) -> Vec<String> {
+        let config = f.path().join("config.toml");
+        let mut argv: Vec<OsString> = vec!["tmuxstar".into(), "--config".into(), config.into()];
+        argv.extend(args.iter().map(OsString::from));
+        let n = argv.len();
+        let merged = merged_args(cmd(), argv);
+        merged[n..].iter().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn file_fills_what_the_command_line_left_out() {
+        let f = Fixture::new("config-file", &[("config.toml", FILE)]);
+        let mut got = added(&f, &["git", "--icon", "cli"]);
+        got.sort();
+        assert_eq!(got, ["--counts=staged,untracked", "--format=file", "--stale"]);
+    }
+
+    #[test]
+    fn profile_over_file() {
+        let f = Fixture::new("config-profile", &[("config.toml", FILE)]);
+        let got = added(&f, &["--profile", "work", "git", "--format", "cli"]);
+        assert!(!got.iter().any(|a| a.starts_with("--format")), "{got:?}");
+        let got = added(&f, &["--profile", "work", "git"]);
+        assert!(got.contains(&"--format=work".to_string()), "{got:?}");
+        assert!(!got.contains(&"--format=file".to_string()), "{got:?}");
+        assert!(got.contains(&"--icon=file".to_string()), "{got:?}");
+    }
+
+    #[test]
+    fn false_flags_and_usage_errors_add_nothing() {
+        let f = Fixture::new("config-false", &[("config.toml", "[git]\nstale = false\n")]);
+        assert!(added(&f, &["git"]).is_empty());
+        // left for clap to report against what the user typed
+        let f = Fixture::new("config-error", &[("config.toml", FILE)]);
+        assert!(added(&f, &["git", "--nope"]).is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+pub const PROC_STAT: &str = "/proc/stat";
+
+/// Cumulative jiffies per CPU line of /proc/stat; index 0 is the "cpu"
+/// aggregate, then cpu0, cpu1, ...
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    /// (busy, total)
+    pub cpus: Vec<(u64, u64)>,
+}
+
+impl Sample {
+    pub fn read(path: &Path) -> Option<Sample> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let cpus: Vec<(u64, u64)> = text
+            .lines()
+            .take_while(|l| l.starts_with("cpu"))
+            .filter_map(parse_line)
+            .collect();
+        if cpus.is_empty() { None } else { Some(Sample { cpus }) }
+    }
+
+    /// Percent busy per line between `prev` and `self`. Lines missing from
+    /// `prev` (first run, CPU hotplug) are measured since boot.
+    pub fn usage_since(&self, prev: &Sample) -> Vec<f64> {
+        self.cpus
+            .iter()
+            .enumerate()
+            .map(|(i, &(busy, total))| {
+                let (pb, pt) = prev.cpus.get(i).copied().unwrap_or((0, 0));
+                // counters only go backwards across a reboot
+                let (pb, pt) = if pt > total || pb > busy { (0, 0) } else { (pb, pt) };
+                let dt = total - pt;
+                if dt == 0 { 0.0 } else { (busy - pb) as f64 * 100.0 / dt as f64 }
+            })
+            .collect()
+    }
+
+    pub fn encode(&self) -> String {
+        self.cpus.iter().map(|(b, t)| format!("{b} {t}\n")).collect()
+    }
+
+    pub fn decode(s: &str) -> Sample {
+        let cpus = s
+            .lines()
+            .filter_map(|l| {
+                let (b, t) = l.split_once(' ')?;
+                Some((b.parse().ok()?, t.parse().ok()?))
+            })
+            .collect();
+        Sample { cpus }
+    }
+}
+
+// cpu  user nice system idle iowait irq softirq steal guest guest_nice
+fn parse_line(l: &str) -> Option<(u64, u64)> {
+    let f: Vec<u64> = l.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect();
+    if f.len() < 4 {
+        return None;
+    }
+    // guest time is already counted in user/nice
+    let total: u64 = f.iter().take(8).sum();
+    let idle = f[3] + f.get(4).copied().unwrap_or(0);
+    Some((total - idle, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    const STAT: &str = "\
+cpu  100 0 50 800 50 0 0 0 20 0
+cpu0 60 0 30 400 10 0 0 0 20 0
+cpu1 40 0 20 400 40 0 0 0 0 0
+intr 12345 0 0
+cpu9 this line comes after the cpu block
+";
+
+    #[test]
+    fn reads_the_cpu_lines() {
+        let f = Fixture::new("cpu-stat", &[("stat", STAT)]);
+        let s = Sample::read(&f.path().join("stat")).unwrap();
+        // guest (20) is left out of the total, iowait counts as idle
+        assert_eq!(s.cpus, vec![(150, 1000), (90, 500), (60, 500)]);
+    }
+
+    #[test]
+    fn usage_between_samples() {
+        let prev = Sample { cpus: vec![(100, 1000), (50, 500)] };
+        let now = Sample { cpus: vec![(150, 1100), (50, 600), (30, 40)] };
+        // cpu1 is new (hotplugged): measured since boot
+        assert_eq!(now.usage_since(&prev), vec![50.0, 0.0, 75.0]);
+    }
+
+    #[test]
+    fn counters_reset_by_a_reboot() {
+        let prev = Sample { cpus: vec![(5000, 90000)] };
+        let now = Sample { cpus: vec![(20, 100)] };
+        assert_eq!(now.usage_since(&prev), vec![20.0]);
+        // no time passed at all
+        assert_eq!(now.usage_since(&now), vec![0.0]);
+    }
+
+    #[test]
+    fn encode_round_trips() {
+        let s = Sample { cpus: vec![(1, 2), (30, 40)] };
+        assert_eq!(Sample::decode(&s.encode()).cpus, s.cpus);
+    }
+}
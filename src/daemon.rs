@@ -0,0 +1,260 @@
+// `tmuxstar daemon`: keeps git segment data cached per pane path and drops
+// entries when inotify reports a change under the repository.
+//
+// Protocol: the client writes its cwd followed by its (config-merged) argv,
+// all NUL-separated, then shuts down its write half. The daemon answers with
+// `+` and the exact stdout the command would have produced, or `-` when it
+// can't serve the request and the client should compute it itself.
+
+use crate::git::{self, BackendKind, Extras, RepoInfo};
+use crate::{Cli, Cmd, json_git, render_git};
+use clap::Parser;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Above this many directories a repo is left to the TTL alone.
+const MAX_WATCHES: usize = 4096;
+
+pub fn socket_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("TMUXSTAR_SOCKET") {
+        return p.into();
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(d) if !d.is_empty() => PathBuf::from(d).join("tmuxstar.sock"),
+        _ => {
+            // /tmp is shared, keep one socket per user
+            let uid = std::fs::metadata("/proc/self").map(|m| m.uid()).unwrap_or(0);
+            std::env::temp_dir().join(format!("tmuxstar-{uid}.sock"))
+        }
+    }
+}
+
+/// Ask a running daemon to render `args`; None means compute it directly.
+/// `timeout_ms` bounds the wait (capped at a second, 0 meaning the cap).
+pub fn query(args: &[OsString], timeout_ms: u64) -> Option<String> {
+    let mut s = UnixStream::connect(socket_path()).ok()?;
+    let timeout = match timeout_ms {
+        0 => 1000,
+        t => t.min(1000),
+    };
+    s.set_read_timeout(Some(Duration::from_millis(timeout))).ok()?;
+
+    let cwd = std::env::current_dir().ok()?;
+    let mut req = cwd.into_os_string().into_vec();
+    for a in args {
+        req.push(0);
+        req.extend_from_slice(a.as_bytes());
+    }
+    s.write_all(&req).ok()?;
+    s.shutdown(Shutdown::Write).ok()?;
+
+    let mut resp = String::new();
+    s.read_to_string(&mut resp).ok()?;
+    resp.strip_prefix('+').map(str::to_string)
+}
+
+struct Entry {
+    info: Option<RepoInfo>,
+    at: Instant,
+}
+
+#[derive(Default)]
+struct State {
+    cache: HashMap<(PathBuf, BackendKind, Extras), Entry>,
+    /// Repos whose directories are (being) watched
+    repos: HashSet<PathBuf>,
+    /// The repo root and directory behind each watch
+    watched: HashMap<WatchDescriptor, (PathBuf, PathBuf)>,
+    // bumped on every invalidation, so a read racing a change isn't cached
+    generation: u64,
+}
+
+impl State {
+    // Watches stay: they outlive the cache entries they invalidate.
+    fn invalidate(&mut self, root: &Path) {
+        self.cache.retain(|_, e| e.info.as_ref().is_none_or(|i| i.root != root));
+        self.generation += 1;
+    }
+
+    fn add_watches(&mut self, root: &Path, wds: Vec<(WatchDescriptor, PathBuf)>) {
+        for (wd, dir) in wds {
+            self.watched.insert(wd, (root.to_path_buf(), dir));
+        }
+    }
+}
+
+pub fn run(socket: &Path, ttl: Duration) -> io::Result<()> {
+    if UnixStream::connect(socket).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AddrInUse, "daemon already running"));
+    }
+    let _ = std::fs::remove_file(socket);
+    let listener = UnixListener::bind(socket)?;
+
+    let inotify = Inotify::init()?;
+    let watches = inotify.watches();
+    let state = Arc::new(Mutex::new(State::default()));
+
+    {
+        let state = Arc::clone(&state);
+        let watches = watches.clone();
+        std::thread::spawn(move || watch_loop(inotify, watches, state));
+    }
+
+    for stream in listener.incoming().flatten() {
+        let state = Arc::clone(&state);
+        let watches = watches.clone();
+        std::thread::spawn(move || {
+            let _ = serve(stream, &state, watches, ttl);
+        });
+    }
+    Ok(())
+}
+
+fn watch_loop(mut inotify: Inotify, mut watches: Watches, state: Arc<Mutex<State>>) {
+    let mut buf = [0u8; 8192];
+    loop {
+        let Ok(events) = inotify.read_events_blocking(&mut buf) else { return; };
+        let mut created = Vec::new();
+        {
+            let mut st = state.lock().unwrap();
+            for ev in events {
+                // the directory is gone and its watch with it
+                if ev.mask.contains(EventMask::IGNORED) {
+                    st.watched.remove(&ev.wd);
+                    continue;
+                }
+                // lock files come and go around every git command, even reads
+                if ev.name.is_some_and(|n| n.as_bytes().ends_with(b".lock")) {
+                    continue;
+                }
+                let Some((root, dir)) = st.watched.get(&ev.wd).cloned() else { continue; };
+                if ev.mask.contains(EventMask::ISDIR)
+                    && ev.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                    && let Some(name) = ev.name
+                {
+                    created.push((root.clone(), dir.join(name)));
+                }
+                st.invalidate(&root);
+            }
+        }
+        // walked without the lock, like whole repos in `cached`
+        for (root, dir) in created {
+            let wds = watch_new_dir(&root, &dir, &mut watches);
+            state.lock().unwrap().add_watches(&root, wds);
+        }
+    }
+}
+
+fn serve(mut stream: UnixStream, state: &Mutex<State>, watches: Watches, ttl: Duration) -> io::Result<()> {
+    // clients send everything at once and give up after a second themselves;
+    // one that stalls mustn't hold its thread forever
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut req = Vec::new();
+    stream.read_to_end(&mut req)?;
+    let mut parts = req.split(|&b| b == 0).map(|p| OsStr::from_bytes(p).to_os_string());
+    let cwd = PathBuf::from(parts.next().unwrap_or_default());
+    let argv = std::iter::once(OsString::from("tmuxstar")).chain(parts);
+
+    let resp = match Cli::try_parse_from(argv) {
+        Ok(Cli { cmd: Cmd::Git(args), json, .. }) => {
+            let path = cwd.join(args.path.as_deref().unwrap_or("."));
+            match cached(state, watches, &path, args.backend, args.extras(json), ttl) {
+                Some(info) if json => format!("+{}\n", json_git(&info)),
+                Some(info) => format!("+{}\n", render_git(&info, &args)),
+                None if json => "+null\n".to_string(),
+                None => "+".to_string(),
+            }
+        }
+        _ => "-".to_string(),
+    };
+    stream.write_all(resp.as_bytes())
+}
+
+fn cached(state: &Mutex<State>, mut watches: Watches, path: &Path, backend: BackendKind, extras: Extras, ttl: Duration) -> Option<RepoInfo> {
+    let key = (path.to_path_buf(), backend, extras);
+    let generation = {
+        let st = state.lock().unwrap();
+        if let Some(e) = st.cache.get(&key) && e.at.elapsed() < ttl {
+            return e.info.clone();
+        }
+        st.generation
+    };
+
+    let info = git::read(&path.to_string_lossy(), backend, extras);
+
+    // claim the repo, then walk it without blocking other clients
+    if let Some(i) = &info && state.lock().unwrap().repos.insert(i.root.clone()) {
+        let wds = watch_repo(&i.root, &mut watches);
+        state.lock().unwrap().add_watches(&i.root, wds);
+    }
+
+    let mut st = state.lock().unwrap();
+    if st.generation == generation {
+        st.cache.insert(key, Entry { info: info.clone(), at: Instant::now() });
+    }
+    info
+}
+
+// Watch the git dir, its refs, the stash reflog and every non-ignored directory of the worktree.
+fn watch_repo(root: &Path, watches: &mut Watches) -> Vec<(WatchDescriptor, PathBuf)> {
+    let Ok(repo) = git2::Repository::open(root) else { return Vec::new(); };
+
+    let mut dirs = vec![repo.path().to_path_buf()];
+    collect_dirs(&repo.commondir().join("refs"), &mut dirs, &|_| false);
+    // dropping an older stash entry only rewrites its reflog
+    dirs.push(repo.commondir().join("logs").join("refs"));
+
+    let mut tree = Vec::new();
+    collect_dirs(root, &mut tree, &|d| unwatched(&repo, root, d));
+    if tree.len() <= MAX_WATCHES {
+        dirs.extend(tree);
+    }
+    add_watches(dirs, watches)
+}
+
+// A directory created in a watched one, along with whatever is already in it.
+fn watch_new_dir(root: &Path, dir: &Path, watches: &mut Watches) -> Vec<(WatchDescriptor, PathBuf)> {
+    let Ok(repo) = git2::Repository::open(root) else { return Vec::new(); };
+    if unwatched(&repo, root, dir) {
+        return Vec::new();
+    }
+    let mut dirs = Vec::new();
+    collect_dirs(dir, &mut dirs, &|d| unwatched(&repo, root, d));
+    add_watches(dirs, watches)
+}
+
+// Nested repos and ignored directories (build output and the like).
+fn unwatched(repo: &git2::Repository, root: &Path, dir: &Path) -> bool {
+    dir.ends_with(".git")
+        || dir.join(".git").exists()
+        || dir.strip_prefix(root).is_ok_and(|rel| repo.is_path_ignored(rel).unwrap_or(false))
+}
+
+fn add_watches(dirs: Vec<PathBuf>, watches: &mut Watches) -> Vec<(WatchDescriptor, PathBuf)> {
+    let mask = WatchMask::MODIFY | WatchMask::CREATE | WatchMask::DELETE
+        | WatchMask::MOVED_FROM | WatchMask::MOVED_TO | WatchMask::ATTRIB;
+    dirs.into_iter().filter_map(|d| Some((watches.add(&d, mask).ok()?, d))).collect()
+}
+
+fn collect_dirs(dir: &Path, out: &mut Vec<PathBuf>, skip: &dyn Fn(&Path) -> bool) {
+    out.push(dir.to_path_buf());
+    if out.len() > MAX_WATCHES {
+        return;
+    }
+    let Ok(rd) = std::fs::read_dir(dir) else { return; };
+    for e in rd.flatten() {
+        let p = e.path();
+        if e.file_type().is_ok_and(|t| t.is_dir()) && !skip(&p) {
+            collect_dirs(&p, out, skip);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Sizes in bytes, as seen by an unprivileged user (like `df`).
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    pub avail: u64,
+}
+
+impl Usage {
+    pub fn percent(&self) -> f64 {
+        // root-reserved blocks count neither as used nor available
+        let usable = self.used + self.avail;
+        if usable == 0 { 0.0 } else { self.used as f64 * 100.0 / usable as f64 }
+    }
+}
+
+pub fn usage(path: &Path) -> Option<Usage> {
+    let c = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c` is NUL-terminated and `st` is a valid out pointer.
+    if unsafe { libc::statvfs(c.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    let frsize = st.f_frsize as u64;
+    Some(Usage {
+        total: st.f_blocks as u64 * frsize,
+        used: (st.f_blocks as u64).saturating_sub(st.f_bfree as u64) * frsize,
+        avail: st.f_bavail as u64 * frsize,
+    })
+}
+
+/// The mount point holding `path`: the topmost ancestor on the same device.
+pub fn mount_point(path: &Path) -> Option<PathBuf> {
+    let path = path.canonicalize().ok()?;
+    let dev = path.metadata().ok()?.dev();
+    let mut mount = path.as_path();
+    while let Some(parent) = mount.parent() {
+        if parent.metadata().ok()?.dev() != dev {
+            break;
+        }
+        mount = parent;
+    }
+    Some(mount.to_path_buf())
+}
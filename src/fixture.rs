@@ -0,0 +1,32 @@
+// Throwaway directories standing in for /proc and /sys in tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory holding `files` as (relative path, contents); removed
+/// again when dropped. `name` must be unique among the tests.
+pub struct Fixture(PathBuf);
+
+impl Fixture {
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("tmuxstar-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, text) in files {
+            let p = dir.join(path);
+            fs::create_dir_all(p.parent().expect("joined onto dir")).unwrap();
+            fs::write(p, text).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        Fixture(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
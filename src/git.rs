@@ -0,0 +1,564 @@
+use clap::ValueEnum;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum BackendKind {
+    /// Try the in-process reader first, fall back to spawning git
+    Auto,
+    Native,
+    Cli,
+}
+
+pub enum Error {
+    NotRepo,
+    Backend,
+    TimedOut,
+}
+
+#[derive(Clone)]
+pub struct RepoInfo {
+    pub root: PathBuf,
+    pub project: String,
+    pub branch: String,
+    pub status: Status,
+    /// Commits (ahead, behind) the configured upstream; None without one
+    pub upstream: Option<(usize, usize)>,
+    pub stash: usize,
+    /// A tag pointing at HEAD, if any
+    pub tag: Option<String>,
+    pub operation: Option<Operation>,
+}
+
+/// Fields of `RepoInfo` that take extra work to read; those not asked for
+/// are left empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Extras {
+    pub tag: bool,
+    pub stash: bool,
+}
+
+/// A multi-step git command that is stopped half way.
+#[derive(Clone)]
+pub struct Operation {
+    pub name: &'static str,
+    /// (current step, total steps), for rebases and `git am`
+    pub progress: Option<(usize, usize)>,
+    /// Branch being rebased; HEAD itself is detached meanwhile
+    pub branch: Option<String>,
+}
+
+impl Operation {
+    /// E.g. `REBASE 3/7` or `MERGING`, in the style of git's own prompt.
+    pub fn label(&self) -> String {
+        match self.progress {
+            Some((n, total)) => format!("{} {n}/{total}", self.name),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+// Same checks as git-prompt.sh, driven by marker files in the git dir.
+fn operation(git_dir: &Path) -> Option<Operation> {
+    let read = |f: &str| -> Option<String> {
+        let s = std::fs::read_to_string(git_dir.join(f)).ok()?;
+        Some(s.trim().to_string())
+    };
+    let num = |f: &str| -> Option<usize> { read(f)?.parse().ok() };
+    let branch = |f: &str| -> Option<String> {
+        let h = read(f)?;
+        Some(h.strip_prefix("refs/heads/").unwrap_or(&h).to_string())
+    };
+
+    if git_dir.join("rebase-merge").is_dir() {
+        return Some(Operation {
+            name: "REBASE",
+            progress: num("rebase-merge/msgnum").zip(num("rebase-merge/end")),
+            branch: branch("rebase-merge/head-name"),
+        });
+    }
+    if git_dir.join("rebase-apply").is_dir() {
+        let am = git_dir.join("rebase-apply/applying").exists();
+        return Some(Operation {
+            name: if am { "AM" } else { "REBASE" },
+            progress: num("rebase-apply/next").zip(num("rebase-apply/last")),
+            branch: branch("rebase-apply/head-name"),
+        });
+    }
+    let name = [
+        ("MERGE_HEAD", "MERGING"),
+        ("CHERRY_PICK_HEAD", "CHERRY-PICKING"),
+        ("REVERT_HEAD", "REVERTING"),
+        ("BISECT_LOG", "BISECTING"),
+    ]
+    .into_iter()
+    .find(|(f, _)| git_dir.join(f).exists())?
+    .1;
+    Some(Operation { name, progress: None, branch: None })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    pub staged: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+impl Status {
+    /// Collapse the counts into the single state the icon is colored by.
+    pub fn state(&self) -> &'static str {
+        if self.conflicted > 0 {
+            "conflict"
+        } else if self.untracked > 0 {
+            "untracked"
+        } else if self.staged > 0 || self.renamed > 0 {
+            "staged"
+        } else if self.modified > 0 || self.deleted > 0 {
+            "unstaged"
+        } else {
+            "clean"
+        }
+    }
+
+    pub fn count(&self, c: Change) -> usize {
+        match c {
+            Change::Staged     => self.staged,
+            Change::Modified   => self.modified,
+            Change::Deleted    => self.deleted,
+            Change::Renamed    => self.renamed,
+            Change::Untracked  => self.untracked,
+            Change::Conflicted => self.conflicted,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Change {
+    Staged,
+    Modified,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+impl Change {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Change::Staged     => "+",
+            Change::Modified   => "~",
+            Change::Deleted    => "-",
+            Change::Renamed    => "»",
+            Change::Untracked  => "?",
+            Change::Conflicted => "!",
+        }
+    }
+}
+
+pub trait GitBackend {
+    fn read(&self, path: &str, extras: Extras) -> Result<RepoInfo, Error>;
+}
+
+pub fn read(path: &str, kind: BackendKind, extras: Extras) -> Option<RepoInfo> {
+    read_by(path, kind, extras, None).ok()
+}
+
+fn read_by(path: &str, kind: BackendKind, extras: Extras, deadline: Option<Instant>) -> Result<RepoInfo, Error> {
+    let cli = Cli { deadline };
+    match kind {
+        BackendKind::Native => Native.read(path, extras),
+        BackendKind::Cli => cli.read(path, extras),
+        BackendKind::Auto => match Native.read(path, extras) {
+            Err(Error::Backend) => cli.read(path, extras),
+            r => r,
+        },
+    }
+}
+
+pub struct TimedOut;
+
+/// Like `read`, but gives up after `timeout`. libgit2 can't be interrupted,
+/// so the read runs on a thread that is simply abandoned; git children still
+/// running past the deadline are killed.
+pub fn read_within(path: &str, kind: BackendKind, extras: Extras, timeout: Duration) -> Result<Option<RepoInfo>, TimedOut> {
+    let deadline = Instant::now() + timeout;
+    let (tx, rx) = mpsc::channel();
+    let p = path.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(read_by(&p, kind, extras, Some(deadline)));
+    });
+
+    // a little grace so a killed child is reported as a timeout, not a failure
+    match rx.recv_timeout(timeout + Duration::from_millis(50)) {
+        Ok(Err(Error::TimedOut)) | Err(_) => Err(TimedOut),
+        Ok(r) => Ok(r.ok()),
+    }
+}
+
+fn project_name(root: &Path) -> Option<String> {
+    Some(root.file_name()?.to_string_lossy().to_string())
+}
+
+// Native: reads the repository through libgit2, no child processes.
+
+pub struct Native;
+
+impl GitBackend for Native {
+    fn read(&self, path: &str, extras: Extras) -> Result<RepoInfo, Error> {
+        let repo = match git2::Repository::discover(path) {
+            Ok(r) => r,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Err(Error::NotRepo),
+            Err(_) => return Err(Error::Backend),
+        };
+        let Some(root) = repo.workdir() else { return Err(Error::NotRepo); };
+        let root = root.components().collect::<PathBuf>();
+        let project = project_name(&root).ok_or(Error::NotRepo)?;
+        let operation = operation(repo.path());
+        let branch = operation.as_ref().and_then(|o| o.branch.clone())
+            .or_else(|| native_head(&repo))
+            .ok_or(Error::NotRepo)?;
+        let status = native_status(&repo).map_err(|_| Error::Backend)?;
+        let upstream = native_upstream(&repo);
+        let stash = match extras.stash {
+            true => repo.reflog("refs/stash").map(|r| r.len()).unwrap_or(0),
+            false => 0,
+        };
+        let tag = extras.tag.then(|| native_tag(&repo)).flatten();
+
+        Ok(RepoInfo { root, project, branch, status, upstream, stash, tag, operation })
+    }
+}
+
+fn native_head(repo: &git2::Repository) -> Option<String> {
+    let head = match repo.head() {
+        Ok(h) => h,
+        // fresh repo: HEAD points at a branch with no commits yet
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+            let r = repo.find_reference("HEAD").ok()?;
+            let target = r.symbolic_target().ok()??;
+            return Some(target.strip_prefix("refs/heads/").unwrap_or(target).to_string());
+        }
+        Err(_) => return None,
+    };
+    if head.is_branch() {
+        return head.shorthand().ok().map(str::to_string);
+    }
+
+    // detached: name it after a ref sitting exactly on HEAD (tags first, like
+    // `git describe --contains --all`), else fall back to the short hash
+    let oid = head.target()?;
+    let mut on_head: Vec<git2::Reference> = repo
+        .references()
+        .map(|refs| refs.flatten().filter(|r| r.target() == Some(oid)).collect())
+        .unwrap_or_default();
+    on_head.sort_by_key(|r| (!r.is_tag(), !r.is_branch()));
+    for r in on_head {
+        let name = if r.is_branch() { r.shorthand() } else { r.name() };
+        if (r.is_tag() || r.is_branch() || r.is_remote())
+            && let Some(name) = name.ok()
+        {
+            return Some(name.strip_prefix("refs/").unwrap_or(name).to_string());
+        }
+    }
+    let obj = repo.find_object(oid, None).ok()?;
+    obj.short_id().ok()?.as_str().ok().map(str::to_string)
+}
+
+fn native_upstream(repo: &git2::Repository) -> Option<(usize, usize)> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let local = head.target()?;
+    let upstream = git2::Branch::wrap(head).upstream().ok()?;
+    let remote = upstream.get().target()?;
+    repo.graph_ahead_behind(local, remote).ok()
+}
+
+fn native_tag(repo: &git2::Repository) -> Option<String> {
+    let head = repo.head().ok()?.peel_to_commit().ok()?.id();
+    let mut first: Option<String> = None;
+    repo.tag_foreach(|oid, name| {
+        // lightweight tags point at the commit itself; annotated ones are
+        // peeled through their tag object
+        let on_head = oid == head
+            || repo.find_tag(oid).ok()
+                .and_then(|t| t.target().ok()?.peel_to_commit().ok())
+                .is_some_and(|c| c.id() == head);
+        let name = String::from_utf8_lossy(name);
+        let name = name.strip_prefix("refs/tags/").unwrap_or(&name);
+        if on_head && first.as_deref().is_none_or(|f| name < f) {
+            first = Some(name.to_string());
+        }
+        true
+    })
+    .ok()?;
+    first
+}
+
+fn native_status(repo: &git2::Repository) -> Result<Status, git2::Error> {
+    use git2::Status as S;
+
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .renames_head_to_index(true);
+    let statuses = repo.statuses(Some(&mut opts))?;
+
+    let staged = S::INDEX_NEW | S::INDEX_MODIFIED | S::INDEX_DELETED | S::INDEX_TYPECHANGE;
+    let mut st = Status::default();
+    for f in statuses.iter().map(|e| e.status()) {
+        if f.is_conflicted() {
+            st.conflicted += 1;
+            continue;
+        }
+        if f.is_wt_new() {
+            st.untracked += 1;
+            continue;
+        }
+        // one entry can be both staged and modified, like porcelain "MM"
+        if f.intersects(staged)                         { st.staged   += 1; }
+        if f.is_index_renamed() || f.is_wt_renamed()    { st.renamed  += 1; }
+        if f.is_wt_modified() || f.is_wt_typechange()   { st.modified += 1; }
+        if f.is_wt_deleted()                            { st.deleted  += 1; }
+    }
+    Ok(st)
+}
+
+// Cli: the original implementation, shelling out to `git`.
+
+pub struct Cli {
+    /// git children still running past this are killed
+    pub deadline: Option<Instant>,
+}
+
+impl GitBackend for Cli {
+    fn read(&self, path: &str, extras: Extras) -> Result<RepoInfo, Error> {
+        let info = self.read_all(path, extras);
+        // a killed child leaves its field empty rather than failing the read
+        match self.deadline {
+            Some(d) if Instant::now() >= d => Err(Error::TimedOut),
+            _ => info,
+        }
+    }
+}
+
+impl Cli {
+    fn read_all(&self, path: &str, extras: Extras) -> Result<RepoInfo, Error> {
+        let dl = self.deadline;
+        if !is_repo(path, dl) {
+            return Err(Error::NotRepo);
+        }
+        let root = git_ok(path, dl, &["rev-parse", "--show-toplevel"]).ok_or(Error::NotRepo)?;
+        let root = PathBuf::from(root);
+        let project = project_name(&root).ok_or(Error::NotRepo)?;
+        let operation = git_ok(path, dl, &["rev-parse", "--absolute-git-dir"])
+            .and_then(|d| self::operation(Path::new(&d)));
+        let branch = operation.as_ref().and_then(|o| o.branch.clone())
+            .or_else(|| head_name(path, dl))
+            .ok_or(Error::NotRepo)?;
+        let status = repo_status(path, dl);
+        let upstream = ahead_behind(path, dl);
+        let stash = extras.stash
+            .then(|| git_ok(path, dl, &["rev-list", "--walk-reflogs", "--count", "refs/stash"]))
+            .flatten()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        let tag = extras.tag
+            .then(|| git_ok(path, dl, &["tag", "--points-at", "HEAD"]))
+            .flatten()
+            .and_then(|t| t.lines().next().map(str::to_string));
+
+        Ok(RepoInfo { root, project, branch, status, upstream, stash, tag, operation })
+    }
+}
+
+// Command::output, but bounded by `deadline` when one is set.
+fn output(cmd: &mut Command, deadline: Option<Instant>) -> io::Result<Output> {
+    let Some(deadline) = deadline else { return cmd.output(); };
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // drain stdout meanwhile, a full pipe would stall the child
+    let mut pipe = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    });
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let stdout = reader.join().unwrap_or_default();
+            return Ok(Output { status, stdout, stderr: Vec::new() });
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn git_ok(path: &str, deadline: Option<Instant>, args: &[&str]) -> Option<String> {
+    let out = output(Command::new("git")
+        .args(["-C", path])
+        .args(args), deadline)
+        .ok()?;                    // could not spawn → None
+    if !out.status.success() {
+        return None;               // non-zero exit → None
+    }
+    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    if s.is_empty() { None } else { Some(s) }
+}
+
+fn is_repo(path: &str, deadline: Option<Instant>) -> bool {
+    output(Command::new("git").args(["-C", path, "rev-parse", "--is-inside-working-tree"]), deadline)
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn head_name(path: &str, deadline: Option<Instant>) -> Option<String> {
+    // also names the branch of a repo without commits, which rev-parse can't
+    if let Some(b) = git_ok(path, deadline, &["symbolic-ref", "--short", "-q", "HEAD"]) {
+        return Some(b);
+    }
+    let mut h = git_ok(path, deadline, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    if h == "HEAD" && let Some(d) = git_ok(path, deadline, &["describe", "--contains", "--all", "HEAD"]) {
+        h = d;
+    }
+    Some(h)
+}
+
+fn ahead_behind(path: &str, deadline: Option<Instant>) -> Option<(usize, usize)> {
+    // prints "<ahead>\t<behind>"; fails when there is no upstream
+    let s = git_ok(path, deadline, &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?;
+    let (a, b) = s.split_once('\t')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+fn repo_status(path: &str, deadline: Option<Instant>) -> Status {
+    // Run: git -C <path> status --porcelain
+    // (without optional locks, so a status line never refreshes the index)
+    let mut st = Status::default();
+    let out = match output(Command::new("git")
+        .args(["--no-optional-locks", "-C", path, "status", "--porcelain"]), deadline)
+    {
+        Ok(o) => o,
+        Err(_) => return st, // if git can't run here, treat as clean/none
+    };
+
+    let s = String::from_utf8_lossy(&out.stdout);
+    for l in s.lines() {
+        let Some(xy) = l.get(0..2) else { continue; };
+        if matches!(xy, "UU" | "AA" | "DD" | "AU" | "UD" | "UA" | "DU") {
+            st.conflicted += 1;
+            continue;
+        }
+        if xy == "??" {
+            st.untracked += 1;
+            continue;
+        }
+        let (x, y) = (xy.as_bytes()[0], xy.as_bytes()[1]);
+        if b"MADCT".contains(&x)      { st.staged   += 1; }
+        if x == b'R' || y == b'R'     { st.renamed  += 1; }
+        if b"MT".contains(&y)         { st.modified += 1; }
+        if y == b'D'                  { st.deleted  += 1; }
+    }
+    st
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let ok = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@t", "-c", "commit.gpgsign=false"])
+            .args(["-C", &dir.to_string_lossy()])
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+        // a conflicting merge exits non-zero on purpose
+        assert!(ok || args[0] == "merge", "git {args:?}");
+    }
+
+    fn write(dir: &Path, name: &str, text: &str) {
+        std::fs::write(dir.join(name), text).unwrap();
+    }
+
+    #[test]
+    fn backends_count_changes_alike() {
+        let f = Fixture::new("git-status", &[]);
+        let dir = f.path();
+        git(dir, &["init", "-q", "-b", "main"]);
+        for name in ["modified", "deleted", "both", "conflict"] {
+            write(dir, name, &format!("{name}\n"));
+        }
+        write(dir, "renamed", "a file long enough for rename detection\n");
+        git(dir, &["add", "-A"]);
+        git(dir, &["commit", "-qm", "base"]);
+
+        git(dir, &["checkout", "-qb", "other"]);
+        write(dir, "conflict", "theirs\n");
+        git(dir, &["commit", "-qam", "theirs"]);
+        git(dir, &["checkout", "-q", "main"]);
+        write(dir, "conflict", "ours\n");
+        git(dir, &["commit", "-qam", "ours"]);
+        git(dir, &["merge", "-q", "other"]);
+
+        write(dir, "new", "new\n");
+        git(dir, &["add", "new"]);
+        write(dir, "modified", "changed\n");
+        std::fs::remove_file(dir.join("deleted")).unwrap();
+        git(dir, &["mv", "renamed", "moved"]);
+        // staged, then changed again: counts on both sides, like "MM"
+        write(dir, "both", "staged\n");
+        git(dir, &["add", "both"]);
+        write(dir, "both", "and modified\n");
+        write(dir, "untracked", "?\n");
+
+        let want = Status { staged: 2, modified: 2, deleted: 1, renamed: 1, untracked: 1, conflicted: 1 };
+        let path = dir.to_string_lossy();
+        let native = Native.read(&path, Extras::default()).ok().unwrap();
+        let cli = Cli { deadline: None }.read(&path, Extras::default()).ok().unwrap();
+        assert_eq!(native.status, want);
+        assert_eq!(cli.status, want);
+        assert_eq!(native.status.state(), "conflict");
+        assert_eq!((native.branch.as_str(), cli.branch.as_str()), ("main", "main"));
+    }
+
+    #[test]
+    fn clean_and_unborn_repos() {
+        let f = Fixture::new("git-clean", &[]);
+        let dir = f.path();
+        git(dir, &["init", "-q", "-b", "trunk"]);
+        let path = dir.to_string_lossy();
+        for kind in [BackendKind::Native, BackendKind::Cli, BackendKind::Auto] {
+            let info = read(&path, kind, Extras::default()).unwrap();
+            assert_eq!((info.branch.as_str(), info.status), ("trunk", Status::default()), "{kind:?}");
+        }
+
+        let outside = Fixture::new("git-none", &[]);
+        for kind in [BackendKind::Native, BackendKind::Cli, BackendKind::Auto] {
+            assert!(read(&outside.path().to_string_lossy(), kind, Extras::default()).is_none(), "{kind:?}");
+        }
+    }
+}
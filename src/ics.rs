@@ -0,0 +1,317 @@
+// Just enough iCalendar (RFC 5545) to find the next event: VEVENT blocks
+// with a DTSTART and SUMMARY, repeated by simple RRULEs (FREQ=DAILY or
+// WEEKLY with INTERVAL, COUNT, UNTIL and, for WEEKLY, BYDAY). An event with
+// any other rule only counts at its DTSTART; EXDATE and RECURRENCE-ID
+// overrides are ignored.
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::path::Path;
+
+pub struct Event {
+    pub start: DateTime<Utc>,
+    pub summary: String,
+}
+
+/// The earliest event (or repeat of one) starting after `now`.
+pub fn next_event(path: &Path, now: DateTime<Utc>) -> Option<Event> {
+    let text = std::fs::read_to_string(path).ok()?;
+    parse(&text)
+        .into_iter()
+        .filter_map(|e| Some(Event { start: e.next_after(now)?, summary: e.summary }))
+        .min_by_key(|e| e.start)
+}
+
+// A VEVENT as written, before its repeats are worked out.
+#[derive(Default)]
+struct Vevent {
+    start: Option<Start>,
+    summary: String,
+    rule: Option<Rule>,
+}
+
+impl Vevent {
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = self.start?;
+        let Some(rule) = &self.rule else {
+            return start.zone.utc(start.local).filter(|t| *t > now);
+        };
+        for (n, local) in rule.occurrences(start.local).enumerate() {
+            if rule.count.is_some_and(|c| n as u64 >= c) {
+                return None;
+            }
+            // a wall-clock time skipped by a DST change doesn't happen
+            let Some(t) = start.zone.utc(local) else { continue; };
+            if rule.until.is_some_and(|u| t > u) {
+                return None;
+            }
+            if t > now {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+// DTSTART as a wall-clock time in its zone, so repeats keep their local time
+// across DST changes.
+#[derive(Clone, Copy)]
+struct Start {
+    local: NaiveDateTime,
+    zone: Zone,
+}
+
+#[derive(Clone, Copy)]
+enum Zone {
+    Utc,
+    Tz(Tz),
+    /// floating times are taken as local
+    Local,
+}
+
+impl Zone {
+    fn utc(self, t: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Utc => Some(t.and_utc()),
+            Zone::Tz(tz) => Some(tz.from_local_datetime(&t).earliest()?.with_timezone(&Utc)),
+            Zone::Local => Some(Local.from_local_datetime(&t).earliest()?.with_timezone(&Utc)),
+        }
+    }
+}
+
+struct Rule {
+    /// days between repeats, or between the weeks BYDAY picks from
+    step: u64,
+    count: Option<u64>,
+    until: Option<DateTime<Utc>>,
+    days: Vec<Weekday>,
+}
+
+impl Rule {
+    // Every repeat from `start` on, including `start` itself.
+    fn occurrences(&self, start: NaiveDateTime) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        let date = start.date();
+        // BYDAY picks days of each repeated week, which starts on Monday
+        let (base, mut offsets): (NaiveDate, Vec<u64>) = if self.days.is_empty() {
+            (date, vec![0])
+        } else {
+            let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+            (monday, self.days.iter().map(|d| d.num_days_from_monday() as u64).collect())
+        };
+        offsets.sort_unstable();
+        offsets.dedup();
+        (0u64..)
+            .flat_map(move |k| offsets.clone().into_iter().map(move |o| k.saturating_mul(self.step).saturating_add(o)))
+            .map_while(move |n| base.checked_add_days(Days::new(n)))
+            .filter(move |d| *d >= date)
+            .map(move |d| d.and_time(start.time()))
+    }
+}
+
+// None for rules this module can't expand, so only DTSTART counts.
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut freq = None;
+    let mut rule = Rule { step: 1, count: None, until: None, days: Vec::new() };
+    for part in value.split(';') {
+        let (key, val) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(val.to_ascii_uppercase()),
+            "INTERVAL" => rule.step = val.parse().ok().filter(|&n| n > 0)?,
+            "COUNT" => rule.count = Some(val.parse().ok()?),
+            "UNTIL" => rule.until = Some(parse_until(val)?),
+            "BYDAY" => rule.days = val.split(',').map(parse_day).collect::<Option<_>>()?,
+            "WKST" if val.eq_ignore_ascii_case("MO") => {}
+            _ => return None,
+        }
+    }
+    match freq.as_deref() {
+        Some("DAILY") if rule.days.is_empty() => {}
+        Some("WEEKLY") => rule.step = rule.step.saturating_mul(7),
+        _ => return None,
+    }
+    Some(rule)
+}
+
+// Only plain days; `1MO` style ordinals belong to monthly rules.
+fn parse_day(s: &str) -> Option<Weekday> {
+    Some(match s.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+// UNTIL is inclusive; a bare date covers that whole day.
+fn parse_until(value: &str) -> Option<DateTime<Utc>> {
+    let until = parse_dt("", value)?;
+    let end = match value.len() {
+        8 => until.local + chrono::Duration::seconds(24 * 60 * 60 - 1),
+        _ => until.local,
+    };
+    until.zone.utc(end)
+}
+
+// Long lines are folded by a line break followed by a space or tab.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.lines() {
+        let raw = raw.trim_end_matches('\r');
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(cont), Some(last)) => last.push_str(cont),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse(text: &str) -> Vec<Vevent> {
+    let mut events = Vec::new();
+    // components open at this line, innermost last: a VALARM's SUMMARY is
+    // not its event's
+    let mut open: Vec<String> = Vec::new();
+    let mut current = Vevent::default();
+    for line in unfold(text) {
+        let Some((name, value)) = line.split_once(':') else { continue; };
+        let (prop, params) = name.split_once(';').unwrap_or((name, ""));
+        match prop.to_ascii_uppercase().as_str() {
+            "BEGIN" => {
+                let component = value.to_ascii_uppercase();
+                if component == "VEVENT" {
+                    current = Vevent::default();
+                }
+                open.push(component);
+            }
+            "END" => match open.pop() {
+                Some(c) if c == "VEVENT" => events.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            _ if open.last().map(String::as_str) != Some("VEVENT") => {}
+            "DTSTART" => current.start = parse_dt(params, value),
+            "SUMMARY" => current.summary = unescape(value),
+            "RRULE" => current.rule = parse_rule(value),
+            _ => {}
+        }
+    }
+    events
+}
+
+// `20261018T090000Z` (UTC), `TZID=Europe/London:20261018T090000`,
+// `20261018T090000` (floating, taken as local) or `VALUE=DATE:20261018`.
+fn parse_dt(params: &str, value: &str) -> Option<Start> {
+    if let Some(v) = value.strip_suffix('Z') {
+        let local = NaiveDateTime::parse_from_str(v, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Start { local, zone: Zone::Utc });
+    }
+    let local = match NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        Ok(t) => t,
+        Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0)?,
+    };
+    let tzid = params.split(';').find_map(|p| p.strip_prefix("TZID="));
+    let zone = match tzid.and_then(|z| z.trim_matches('"').parse::<Tz>().ok()) {
+        Some(tz) => Zone::Tz(tz),
+        None => Zone::Local,
+    };
+    Some(Start { local, zone })
+}
+
+// One pass, so an escaped backslash can't pair up with the next character.
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push(' '),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn next(ics: &str, now: &str) -> Option<(DateTime<Utc>, String)> {
+        parse(ics)
+            .into_iter()
+            .filter_map(|e| Some((e.next_after(at(now))?, e.summary)))
+            .min_by_key(|e| e.0)
+    }
+
+    fn event(props: &str) -> String {
+        format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\n{props}\nEND:VEVENT\nEND:VCALENDAR\n")
+    }
+
+    #[test]
+    fn alarm_summary_stays_with_the_alarm() {
+        let ics = event("DTSTART:20261019T090000Z\nSUMMARY:Standup\nBEGIN:VALARM\nSUMMARY:Reminder\nEND:VALARM");
+        assert_eq!(next(&ics, "2026-10-18 00:00"), Some((at("2026-10-19 09:00"), "Standup".into())));
+    }
+
+    #[test]
+    fn past_event_without_rule_is_gone() {
+        let ics = event("DTSTART:20261001T090000Z\nSUMMARY:Once");
+        assert_eq!(next(&ics, "2026-10-18 00:00"), None);
+    }
+
+    #[test]
+    fn daily_with_interval() {
+        let ics = event("DTSTART:20261001T090000Z\nRRULE:FREQ=DAILY;INTERVAL=2\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-18 10:00").unwrap().0, at("2026-10-19 09:00"));
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // 2026-10-05 is a Monday
+        let ics = event("DTSTART:20261005T090000Z\nRRULE:FREQ=WEEKLY;BYDAY=WE,MO\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-19 10:00").unwrap().0, at("2026-10-21 09:00"));
+        assert_eq!(next(&ics, "2026-10-21 10:00").unwrap().0, at("2026-10-26 09:00"));
+    }
+
+    #[test]
+    fn count_and_until_end_the_repeats() {
+        let ics = event("DTSTART:20261001T090000Z\nRRULE:FREQ=DAILY;COUNT=3\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-02 10:00").unwrap().0, at("2026-10-03 09:00"));
+        assert_eq!(next(&ics, "2026-10-03 10:00"), None);
+
+        let ics = event("DTSTART:20261001T090000Z\nRRULE:FREQ=WEEKLY;UNTIL=20261015\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-09 00:00").unwrap().0, at("2026-10-15 09:00"));
+        assert_eq!(next(&ics, "2026-10-15 10:00"), None);
+    }
+
+    #[test]
+    fn unsupported_rule_keeps_only_dtstart() {
+        let ics = event("DTSTART:20261020T090000Z\nRRULE:FREQ=MONTHLY;BYDAY=1MO\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-18 00:00").unwrap().0, at("2026-10-20 09:00"));
+        assert_eq!(next(&ics, "2026-10-21 00:00"), None);
+    }
+
+    #[test]
+    fn repeats_keep_local_time_across_dst() {
+        // London leaves summer time on 2026-10-25
+        let ics = event("DTSTART;TZID=Europe/London:20261023T090000\nRRULE:FREQ=DAILY\nSUMMARY:x");
+        assert_eq!(next(&ics, "2026-10-23 09:00").unwrap().0, at("2026-10-24 08:00"));
+        assert_eq!(next(&ics, "2026-10-25 00:00").unwrap().0, at("2026-10-25 09:00"));
+    }
+
+    #[test]
+    fn unescape_is_one_pass() {
+        assert_eq!(unescape(r"a\\nb"), r"a\nb");
+        assert_eq!(unescape(r"Lunch\, then\nwalk\;"), "Lunch, then walk;");
+        assert_eq!(unescape(r"trailing\"), r"trailing\");
+    }
+}
@@ -0,0 +1,203 @@
+// `tmuxstar install` / `uninstall`: keep a managed block of status settings
+// in tmux.conf, between marker comments so it can be replaced or removed
+// without touching anything else in the file.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const BEGIN: &str = "# >>> tmuxstar >>>";
+const END: &str = "# <<< tmuxstar <<<";
+
+/// ~/.tmux.conf, or the XDG location when only that one exists.
+pub fn default_conf() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let classic = home.join(".tmux.conf");
+    let xdg = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => home.join(".config"),
+    }
+    .join("tmux")
+    .join("tmux.conf");
+    if !classic.exists() && xdg.exists() { Some(xdg) } else { Some(classic) }
+}
+
+/// The managed block for `settings`, given as (option, value) pairs.
+pub fn block(settings: &[(&str, String)]) -> String {
+    let mut out = format!("{BEGIN}\n");
+    for (opt, val) in settings {
+        out.push_str(&format!("set -g {opt} {val}\n"));
+    }
+    out.push_str(END);
+    out.push('\n');
+    out
+}
+
+/// Double-quotes `s` for tmux.conf, where `$` would expand an environment
+/// variable and `\` starts an escape.
+pub fn tmux_quote(s: &str) -> String {
+    let s = s.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$");
+    format!("\"{s}\"")
+}
+
+/// Escapes `s` for a tmux status line, which goes through strftime and then
+/// format expansion before `#()` runs it. `#{...}` formats such as
+/// `#{pane_id}` are kept for tmux to fill in; any other `#` is doubled so
+/// e.g. a color like `#D0…` isn't read as the `#D` alias.
+pub fn format_escape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => out.push_str("%%"),
+            '#' if chars.peek() != Some(&'{') => out.push_str("##"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Single-quotes `s` for the shell tmux runs `#()` commands with.
+pub fn sh_quote(s: &str) -> String {
+    // `#` only starts a comment at the beginning of a word
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,%{}#".contains(c);
+    if !s.is_empty() && !s.starts_with('#') && s.chars().all(plain) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// What `write` did to the file.
+pub enum Written {
+    Unchanged,
+    /// There was no file before
+    Created,
+    /// With the path the previous version was saved to, when it was saved
+    /// this time
+    Updated(Option<PathBuf>),
+}
+
+/// Replaces the managed block in `conf` with `block` (None removes it).
+/// The first time an existing file is changed it is copied to
+/// `<conf>.bak`; later changes keep that copy of the user's own version.
+pub fn write(conf: &Path, block: Option<&str>) -> io::Result<Written> {
+    let old = match std::fs::read_to_string(conf) {
+        Ok(t) => Some(t),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let text = old.as_deref().unwrap_or_default();
+    let mut new = without_block(text)?;
+    if let Some(b) = block {
+        if !new.is_empty() && !new.ends_with("\n\n") {
+            new.push_str(if new.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        new.push_str(b);
+    }
+    if text == new {
+        return Ok(Written::Unchanged);
+    }
+
+    let written = match &old {
+        Some(t) => {
+            let mut name = conf.as_os_str().to_owned();
+            name.push(".bak");
+            let backup = PathBuf::from(name);
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&backup) {
+                Ok(mut f) => {
+                    f.write_all(t.as_bytes())?;
+                    Written::Updated(Some(backup))
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Written::Updated(None),
+                Err(e) => return Err(e),
+            }
+        }
+        None => Written::Created,
+    };
+    if let Some(dir) = conf.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(conf, new)?;
+    Ok(written)
+}
+
+// `text` minus the managed block and the blank line before it. A block
+// whose end marker was deleted is an error: where it ends is anyone's guess.
+fn without_block(text: &str) -> io::Result<String> {
+    let Some(start) = text.find(BEGIN) else { return Ok(text.to_string()); };
+    let Some(end) = text[start..].find(END).map(|i| start + i + END.len()) else {
+        return Err(io::Error::other(format!("unterminated tmuxstar block: `{BEGIN}` without `{END}`")));
+    };
+    let head = text[..start].trim_end_matches('\n');
+    let tail = text[end..].trim_start_matches('\n');
+    Ok(match (head.is_empty(), tail.is_empty()) {
+        (true, _) => tail.to_string(),
+        (false, true) => format!("{head}\n"),
+        (false, false) => format!("{head}\n\n{tail}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn settings(interval: &str) -> String {
+        block(&[("status-interval", interval.to_string())])
+    }
+
+    #[test]
+    fn missing_end_marker_leaves_the_file_alone() {
+        let text = format!("{BEGIN}\nbind r source-file ~/.tmux.conf\nset -g mouse on\n");
+        let f = Fixture::new("install-unterminated", &[("tmux.conf", &text)]);
+        let conf = f.path().join("tmux.conf");
+        assert!(write(&conf, None).is_err());
+        assert!(write(&conf, Some(&settings("5"))).is_err());
+        assert_eq!(std::fs::read_to_string(&conf).unwrap(), text);
+        assert!(!f.path().join("tmux.conf.bak").exists());
+    }
+
+    #[test]
+    fn block_in_the_middle() {
+        let text = format!("set -g mouse on\n\n{}\nbind r source-file ~/.tmux.conf\n", settings("5"));
+        assert_eq!(without_block(&text).unwrap(), "set -g mouse on\n\nbind r source-file ~/.tmux.conf\n");
+        assert_eq!(without_block("set -g mouse on\n").unwrap(), "set -g mouse on\n");
+        assert_eq!(without_block(&settings("5")).unwrap(), "");
+    }
+
+    #[test]
+    fn replaces_in_place_and_keeps_the_first_backup() {
+        let f = Fixture::new("install-write", &[("tmux.conf", "set -g mouse on\n")]);
+        let conf = f.path().join("tmux.conf");
+        let bak = f.path().join("tmux.conf.bak");
+
+        assert!(matches!(write(&conf, Some(&settings("5"))).unwrap(), Written::Updated(Some(ref b)) if *b == bak));
+        assert_eq!(std::fs::read_to_string(&conf).unwrap(), format!("set -g mouse on\n\n{}", settings("5")));
+        assert!(matches!(write(&conf, Some(&settings("5"))).unwrap(), Written::Unchanged));
+
+        assert!(matches!(write(&conf, Some(&settings("2"))).unwrap(), Written::Updated(None)));
+        assert_eq!(std::fs::read_to_string(&conf).unwrap(), format!("set -g mouse on\n\n{}", settings("2")));
+        assert_eq!(std::fs::read_to_string(&bak).unwrap(), "set -g mouse on\n");
+
+        assert!(matches!(write(&conf, None).unwrap(), Written::Updated(None)));
+        assert_eq!(std::fs::read_to_string(&conf).unwrap(), "set -g mouse on\n");
+        assert!(matches!(write(&conf, None).unwrap(), Written::Unchanged));
+    }
+
+    #[test]
+    fn creates_a_missing_file_but_not_for_uninstall() {
+        let f = Fixture::new("install-create", &[]);
+        let conf = f.path().join("tmux").join("tmux.conf");
+        assert!(matches!(write(&conf, None).unwrap(), Written::Unchanged));
+        assert!(!conf.exists());
+        assert!(matches!(write(&conf, Some(&settings("5"))).unwrap(), Written::Created));
+        assert_eq!(std::fs::read_to_string(&conf).unwrap(), settings("5"));
+    }
+
+    #[test]
+    fn format_escape_keeps_tmux_formats() {
+        assert_eq!(format_escape("%H:%M"), "%%H:%%M");
+        assert_eq!(format_escape("#D08770"), "##D08770");
+        assert_eq!(format_escape("#{pane_id}"), "#{pane_id}");
+        assert_eq!(format_escape("100%#"), "100%%##");
+    }
+}
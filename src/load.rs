@@ -0,0 +1,45 @@
+use std::path::Path;
+use std::time::Duration;
+
+pub const PROC_LOADAVG: &str = "/proc/loadavg";
+pub const PROC_UPTIME: &str = "/proc/uptime";
+
+/// 1, 5 and 15 minute load averages.
+pub fn loadavg(path: &Path) -> Option<[f64; 3]> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let mut f = text.split_whitespace().map(|v| v.parse::<f64>().ok());
+    Some([f.next()??, f.next()??, f.next()??])
+}
+
+pub fn uptime(path: &Path) -> Option<Duration> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let secs: f64 = text.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(secs))
+}
+
+pub fn cores() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn reads_loadavg_and_uptime() {
+        let f = Fixture::new("load-proc", &[
+            ("loadavg", "0.52 1.25 2.00 3/1234 56789\n"),
+            ("uptime", "93784.56 350000.12\n"),
+        ]);
+        assert_eq!(loadavg(&f.path().join("loadavg")), Some([0.52, 1.25, 2.0]));
+        assert_eq!(uptime(&f.path().join("uptime")), Some(Duration::from_secs_f64(93784.56)));
+    }
+
+    #[test]
+    fn short_or_garbled_files() {
+        let f = Fixture::new("load-bad", &[("loadavg", "0.52 1.25\n"), ("uptime", "soon\n")]);
+        assert_eq!(loadavg(&f.path().join("loadavg")), None);
+        assert_eq!(uptime(&f.path().join("uptime")), None);
+    }
+}
@@ -1,155 +1,1559 @@
-use chrono::Local;
-use clap::{Parser, Subcommand};
-use std::path::Path;
-use std::process::Command;
+mod battery;
+mod cache;
+mod clock;
+mod config;
+mod cpu;
+mod daemon;
+mod disk;
+#[cfg(test)]
+mod fixture;
+mod git;
+mod ics;
+mod install;
+mod json;
+mod load;
+mod mem;
+mod net;
+mod output;
+mod status;
+mod template;
+mod theme;
+mod timer;
+mod tmux;
+
+use chrono::{NaiveTime, Utc};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+use std::time::{Duration, Instant};
+use git::{BackendKind, Change, RepoInfo, Status};
+use theme::{Background, Level, LevelColors, Palette, Theme};
 
 #[derive(Parser)]
 #[command(name = "tmuxstar", version)]
 struct Cli {
+    /// Config file [default: $XDG_CONFIG_HOME/tmuxstar/config.toml]
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// Named profile from the config file, layered over its defaults
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Markup to emit colors in
+    #[arg(long, global = true, value_enum, default_value="tmux")]
+    output: output::Output,
+    /// Print the data behind a segment as JSON instead of rendering it
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     cmd: Cmd,
 }
 
 #[derive(Subcommand)]
 enum Cmd {
-    Git {
+    Git(Box<GitArgs>),
+    Time(Box<TimeArgs>),
+    Battery(Box<BatteryArgs>),
+    Cpu(Box<CpuArgs>),
+    Mem(Box<MemArgs>),
+    Load(Box<LoadArgs>),
+    Net(Box<NetArgs>),
+    Disk(Box<DiskArgs>),
+    /// Pomodoro timer; `timer status` is the segment
+    Timer {
+        #[command(subcommand)]
+        cmd: TimerCmd,
+    },
+    /// Render several segments in one run, e.g.
+    /// `status --segment git --segment "time --format %H:%M"`
+    Status(Box<StatusArgs>),
+    /// Add tmuxstar's status settings to tmux.conf and reload tmux
+    Install(Box<InstallArgs>),
+    /// Remove what `install` added
+    Uninstall {
+        /// [default: ~/.tmux.conf]
         #[arg(long)]
-        path:Option<String>,
-        #[arg(long, default_value="white")]
-        label_fg: String,
-        #[arg(long, default_value=" ")]
-        icon: String,
+        file: Option<String>,
     },
-    Time {
-        #[arg(long, default_value="%Y-%m-%d %I:%M%p")]
-        format: String,
-        #[arg(long, default_value="󰸗 ")]
-        icon: String,
+    /// Serve git segments from a cache kept fresh with inotify
+    Daemon {
+        /// [default: $TMUXSTAR_SOCKET, else $XDG_RUNTIME_DIR/tmuxstar.sock]
+        #[arg(long)]
+        socket: Option<String>,
+        /// Seconds before a cached entry is recomputed even without changes
+        #[arg(long, default_value_t=30)]
+        ttl: u64,
     },
 
 }
 
-fn print_time(format: &str, icon: &str) {
-    let now = Local::now();
-    let s = now.format(format).to_string();
-    if icon.is_empty() {
-        print!("{s} ");
-    } else {
-        print!("{icon}{s} ");
+#[derive(Args)]
+struct GitArgs {
+    /// Repository to show [default: the active tmux pane's directory under
+    /// tmux, else the current directory]
+    #[arg(long)]
+    path:Option<String>,
+    /// tmux pane whose directory to use without --path, e.g. #{pane_id}
+    #[arg(long)]
+    pane: Option<String>,
+    /// tmux client whose active pane to use without --path, e.g. #{client_name}
+    #[arg(long)]
+    client: Option<String>,
+    #[arg(long, default_value="white")]
+    label_fg: String,
+    #[arg(long, default_value=" ")]
+    icon: String,
+    #[arg(long, value_enum, default_value="auto")]
+    backend: BackendKind,
+    /// How to show commits ahead of/behind the upstream branch
+    #[arg(long, value_enum, default_value="compact")]
+    ahead_behind: AheadBehind,
+    #[arg(long, default_value="↑")]
+    ahead_icon: String,
+    #[arg(long, default_value="↓")]
+    behind_icon: String,
+    /// When to show the number of stash entries
+    #[arg(long, value_enum, default_value="auto")]
+    stash: StashMode,
+    #[arg(long, default_value="≡")]
+    stash_icon: String,
+    /// Change counts to show, in order, e.g. staged,modified,untracked,conflicted
+    #[arg(long, value_enum, value_delimiter = ',')]
+    counts: Vec<Change>,
+    /// Output template. Placeholders: {project} {branch} {state} {root} {tag}
+    /// {stash} {stashed} {ahead} {behind} {ab} {counts} {staged} {modified} {deleted}
+    /// {renamed} {untracked} {conflicted} {op} {icon} {state_fg} {op_fg}
+    /// {stash_fg} {label_fg}.
+    /// `{?...}` is dropped when any placeholder inside it is empty.
+    #[arg(long, default_value=DEFAULT_GIT_FORMAT)]
+    format: String,
+    /// Color theme for the state icon; the --*-fg flags override single states
+    #[arg(long, value_enum, default_value="dracula")]
+    theme: Theme,
+    #[arg(long)]
+    conflict_fg: Option<String>,
+    #[arg(long)]
+    unstaged_fg: Option<String>,
+    #[arg(long)]
+    staged_fg: Option<String>,
+    #[arg(long)]
+    untracked_fg: Option<String>,
+    #[arg(long)]
+    clean_fg: Option<String>,
+    #[arg(long)]
+    op_fg: Option<String>,
+    #[arg(long)]
+    stash_fg: Option<String>,
+    /// Milliseconds to wait for git before showing the last value; 0 waits forever
+    #[arg(long, default_value_t=2000)]
+    timeout: u64,
+    /// Prefix marking a last-known value shown after a timeout
+    #[arg(long, default_value="⌛")]
+    stale_mark: String,
+    /// Shown after a timeout when there is no earlier value
+    #[arg(long, default_value="…")]
+    placeholder: String,
+    /// Compute directly even if `tmuxstar daemon` is running
+    #[arg(long)]
+    no_daemon: bool,
+    #[command(flatten)]
+    background: Background,
+}
+
+impl GitArgs {
+    // What --format (or --json, which has every field) shows beyond the basics.
+    fn extras(&self, json: bool) -> git::Extras {
+        let shows = |name: &str| json || self.format.contains(&format!("{{{name}}}"));
+        git::Extras {
+            tag: shows("tag"),
+            stash: shows("stash") || shows("stashed"),
+        }
     }
+
+    fn palette(&self) -> Palette {
+        let mut p = self.theme.palette();
+        for (slot, over) in [
+            (&mut p.conflict,  &self.conflict_fg),
+            (&mut p.unstaged,  &self.unstaged_fg),
+            (&mut p.staged,    &self.staged_fg),
+            (&mut p.untracked, &self.untracked_fg),
+            (&mut p.clean,     &self.clean_fg),
+            (&mut p.operation, &self.op_fg),
+            (&mut p.stash,     &self.stash_fg),
+        ] {
+            if let Some(c) = over {
+                slot.clone_from(c);
+            }
+        }
+        p
+    }
+}
+
+const DEFAULT_GIT_FORMAT: &str = "{state_fg}{icon}{label_fg}{project}({branch}{? {ab}}{?|{op_fg}{op}{label_fg}}){? {counts}}{? {stash_fg}{stashed}{label_fg}} ";
+
+#[derive(Args)]
+struct StatusArgs {
+    /// A segment command line, quoted like in a shell; repeat in order
+    #[arg(long = "segment", value_name = "SEGMENT")]
+    segments: Vec<String>,
+    /// Printed between non-empty segments, unless --powerline is set
+    #[arg(long, default_value=" ")]
+    separator: String,
+    /// Join segments with glyphs that blend their --bg colors
+    #[arg(long, value_enum)]
+    powerline: Option<status::Powerline>,
+    /// Which side of the status line this is; right-hand glyphs point left
+    #[arg(long, value_enum, default_value="left")]
+    side: status::Side,
+    /// Background of the status line itself, for glyphs next to it and to
+    /// segments without --bg; with `default` those edges get no glyph
+    #[arg(long, default_value="default")]
+    status_bg: String,
+}
+
+#[derive(Args)]
+struct InstallArgs {
+    /// Segment for status-right, quoted like in a shell; repeat in order
+    #[arg(long = "right", value_name = "SEGMENT", default_values = ["git", "time"])]
+    right: Vec<String>,
+    /// Segment for status-left [default: leave status-left alone]
+    #[arg(long = "left", value_name = "SEGMENT")]
+    left: Vec<String>,
+    /// Passed to every segment that takes --theme
+    #[arg(long, value_enum, default_value="dracula")]
+    theme: Theme,
+    #[arg(long, value_enum)]
+    powerline: Option<status::Powerline>,
+    /// Seconds between status refreshes
+    #[arg(long, default_value_t=5)]
+    interval: u32,
+    /// Maximum width of each status side
+    #[arg(long, default_value_t=120)]
+    length: u32,
+    /// Command tmux runs [default: this executable]
+    #[arg(long)]
+    bin: Option<String>,
+    /// Print the settings instead of editing tmux.conf
+    #[arg(long)]
+    print: bool,
+    /// [default: ~/.tmux.conf]
+    #[arg(long)]
+    file: Option<String>,
+}
+
+#[derive(Args)]
+struct TimeArgs {
+    /// chrono format string (e.g. %V for the ISO week); also the default
+    /// for every --zone
+    #[arg(long, default_value="%Y-%m-%d %I:%M%p")]
+    format: String,
+    #[arg(long, default_value="󰸗 ")]
+    icon: String,
+    /// Show these zones instead of local time, e.g. NYC=America/New_York,
+    /// Europe/London (labelled London) or `local`; repeat for several
+    #[arg(long = "zone", value_name = "ZONE", value_parser = clock::parse_zone)]
+    zones: Vec<clock::Zone>,
+    /// Per-zone output. Placeholders: {fg} {icon} {label} {time}
+    #[arg(long, default_value="{fg}{?{icon}}{label} {time}")]
+    zone_template: String,
+    /// Printed between zones
+    #[arg(long, default_value=" | ")]
+    separator: String,
+    /// Color for every zone; --zone-fg overrides single zones
+    #[arg(long)]
+    fg: Option<String>,
+    /// LABEL=FORMAT, overrides --format for one zone
+    #[arg(long, value_parser = clock::parse_override)]
+    zone_format: Vec<(String, String)>,
+    /// LABEL=ICON, shown before that zone's label
+    #[arg(long, value_parser = clock::parse_override)]
+    zone_icon: Vec<(String, String)>,
+    /// LABEL=COLOR
+    #[arg(long, value_parser = clock::parse_override)]
+    zone_fg: Vec<(String, String)>,
+    /// Count down to the next of these local times, e.g. 17:30; repeatable
+    #[arg(long, value_parser = clock::parse_hm)]
+    until: Vec<NaiveTime>,
+    /// Count down to the next event in this iCalendar file; daily and weekly
+    /// repeats are followed, events with other recurrence rules only count once
+    #[arg(long)]
+    ics: Option<String>,
+    /// Appended for the nearest countdown target. Placeholders: {in} {what}
+    #[arg(long, default_value=" 󰔟 {in}{? {what}}")]
+    countdown_format: String,
+    /// Working hours, e.g. 09:00-17:30; outside them the clock uses --off-fg
+    #[arg(long, value_parser = clock::parse_span)]
+    work_hours: Option<(NaiveTime, NaiveTime)>,
+    #[arg(long, value_enum, value_delimiter = ',', default_value="mon,tue,wed,thu,fri")]
+    work_days: Vec<clock::Day>,
+    #[arg(long, default_value="#6272a4")]
+    off_fg: String,
+    /// On days outside --work-days [default: --off-fg]
+    #[arg(long)]
+    weekend_fg: Option<String>,
+    #[command(flatten)]
+    background: Background,
+}
+
+#[derive(Args)]
+struct BatteryArgs {
+    /// Only this supply, e.g. BAT0 [default: all batteries combined]
+    #[arg(long)]
+    name: Option<String>,
+    /// Placeholders: {fg} {icon} {percent} {state} {remaining}
+    #[arg(long, default_value="{fg}{icon}{percent}%{? {remaining}} ")]
+    format: String,
+    /// Icons from empty to full, picked by charge level
+    #[arg(long, value_delimiter = ',', default_value="󰂎,󰁺,󰁻,󰁼,󰁽,󰁾,󰁿,󰂀,󰂁,󰂂,󰁹")]
+    icons: Vec<String>,
+    #[arg(long, default_value="󰂄")]
+    charging_icon: String,
+    /// Replaces the level icon at or below --crit while discharging
+    #[arg(long, default_value="󰂃")]
+    crit_icon: String,
+    /// Percent at or below which the segment turns to the warn color
+    #[arg(long, default_value_t=30)]
+    warn: u8,
+    #[arg(long, default_value_t=15)]
+    crit: u8,
+    #[arg(long)]
+    charging_fg: Option<String>,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+    #[arg(long, default_value=battery::SYSFS, hide = true)]
+    sysfs: String,
+}
+
+#[derive(Args)]
+struct CpuArgs {
+    /// Placeholders: {fg} {icon} {percent} {max} (busiest core) {cores}
+    /// (one bar per core) {spark} (recent total usage)
+    #[arg(long, default_value="{fg}{icon}{percent}% ")]
+    format: String,
+    #[arg(long, default_value="󰻠 ")]
+    icon: String,
+    /// Number of samples kept for {spark}
+    #[arg(long, default_value_t=8)]
+    history: usize,
+    /// Percent at or above which the segment turns to the warn color
+    #[arg(long, default_value_t=60)]
+    warn: u8,
+    #[arg(long, default_value_t=85)]
+    crit: u8,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+    #[arg(long, default_value=cpu::PROC_STAT, hide = true)]
+    proc_stat: String,
+}
+
+#[derive(Args)]
+struct MemArgs {
+    /// Placeholders: {fg} {icon} {used} {avail} {total} {percent} {bar}
+    /// {swap_used} {swap_total} {swap_percent} {swap_bar}
+    #[arg(long, default_value="{fg}{icon}{used}/{total} ")]
+    format: String,
+    #[arg(long, default_value="󰍛 ")]
+    icon: String,
+    #[arg(long, value_enum, default_value="auto")]
+    unit: Unit,
+    /// Width of {bar} and {swap_bar} in cells
+    #[arg(long, default_value_t=10)]
+    bar_width: usize,
+    /// Percent of RAM used at or above which the segment turns to the warn color
+    #[arg(long, default_value_t=75)]
+    warn: u8,
+    #[arg(long, default_value_t=90)]
+    crit: u8,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+    #[arg(long, default_value=mem::PROC_MEMINFO, hide = true)]
+    meminfo: String,
+}
+
+#[derive(Args)]
+struct LoadArgs {
+    /// Placeholders: {fg} {icon} {load1} {load5} {load15} {cores} {uptime}
+    #[arg(long, default_value="{fg}{icon}{load1} {load5} {load15} ")]
+    format: String,
+    #[arg(long, default_value="󰊚 ")]
+    icon: String,
+    /// Show load divided by the number of cores
+    #[arg(long)]
+    normalize: bool,
+    /// 1-minute load per core at or above which the segment turns to the warn color
+    #[arg(long, default_value_t=0.7)]
+    warn: f64,
+    #[arg(long, default_value_t=1.0)]
+    crit: f64,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+    #[arg(long, default_value=load::PROC_LOADAVG, hide = true)]
+    loadavg: String,
+    #[arg(long, default_value=load::PROC_UPTIME, hide = true)]
+    uptime: String,
+}
+
+#[derive(Args)]
+struct NetArgs {
+    /// `default` (the default-route interface), a name like wlan0, or a
+    /// glob like `en*` whose matches are summed
+    #[arg(long, default_value="default")]
+    iface: String,
+    /// Placeholders: {fg} {icon} {iface} {rx} {tx} {state}
+    #[arg(long, default_value="{fg}{icon}↓{rx} ↑{tx} ")]
+    format: String,
+    #[arg(long, default_value="󰈀 ")]
+    icon: String,
+    #[arg(long, default_value="󰈂 ")]
+    down_icon: String,
+    #[arg(long, value_enum, default_value="auto")]
+    unit: Unit,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+    #[arg(long, default_value=net::SYSFS, hide = true)]
+    sysfs: String,
+    #[arg(long, default_value=net::PROC_ROUTE, hide = true)]
+    proc_route: String,
+    #[arg(long, default_value=net::PROC_DEV, hide = true)]
+    proc_dev: String,
+}
+
+#[derive(Args)]
+struct DiskArgs {
+    /// Filesystem(s) to show, by any path on them; repeat for several
+    /// [default: the active tmux pane's directory under tmux, else the
+    /// current directory]
+    #[arg(long = "path")]
+    paths: Vec<String>,
+    /// tmux pane whose directory to use without --path, e.g. #{pane_id}
+    #[arg(long)]
+    pane: Option<String>,
+    /// tmux client whose active pane to use without --path, e.g. #{client_name}
+    #[arg(long)]
+    client: Option<String>,
+    /// Placeholders: {fg} {icon} {mount} {free} {used} {total} {percent} {bar}
+    #[arg(long, default_value="{fg}{icon}{free} ")]
+    format: String,
+    /// Printed between filesystems
+    #[arg(long, default_value="")]
+    separator: String,
+    #[arg(long, default_value="󰋊 ")]
+    icon: String,
+    #[arg(long, value_enum, default_value="auto")]
+    unit: Unit,
+    #[arg(long, default_value_t=10)]
+    bar_width: usize,
+    /// Percent used at or above which the segment turns to the warn color
+    #[arg(long, default_value_t=80)]
+    warn: u8,
+    #[arg(long, default_value_t=90)]
+    crit: u8,
+    #[command(flatten)]
+    colors: LevelColors,
+    #[command(flatten)]
+    background: Background,
+}
+
+#[derive(Subcommand)]
+enum TimerCmd {
+    /// Start a work phase, or resume a paused timer with the lengths it was
+    /// started with
+    Start(TimerStartArgs),
+    Pause,
+    Stop,
+    Status(Box<TimerStatusArgs>),
+}
+
+#[derive(Args)]
+struct TimerStartArgs {
+    /// Minutes per work phase
+    #[arg(long, default_value_t=25)]
+    work: u64,
+    /// Minutes per short break
+    #[arg(long = "break", default_value_t=5)]
+    short_break: u64,
+    #[arg(long, default_value_t=15)]
+    long_break: u64,
+    /// Work phases before a long break
+    #[arg(long, default_value_t=4)]
+    rounds: u32,
+}
+
+#[derive(Args)]
+struct TimerStatusArgs {
+    /// Placeholders: {fg} {icon} {phase} {left} (minutes:seconds remaining)
+    /// {ends} {round}
+    #[arg(long, default_value="{fg}{icon}{left} ")]
+    format: String,
+    /// chrono format for {ends}, when the phase ends (empty while paused)
+    #[arg(long, default_value="%H:%M")]
+    ends_format: String,
+    #[arg(long, default_value="󰔛 ")]
+    work_icon: String,
+    #[arg(long, default_value="󰅶 ")]
+    break_icon: String,
+    #[arg(long, default_value="󰏤 ")]
+    paused_icon: String,
+    #[arg(long, value_enum, default_value="dracula")]
+    theme: Theme,
+    #[arg(long)]
+    work_fg: Option<String>,
+    #[arg(long)]
+    break_fg: Option<String>,
+    #[arg(long)]
+    paused_fg: Option<String>,
+    /// Shell command run when a phase ends, with $TMUXSTAR_PHASE (the phase
+    /// that ended) and $TMUXSTAR_NEXT set
+    #[arg(long)]
+    on_end: Option<String>,
+    #[command(flatten)]
+    background: Background,
+}
+
+/// Binary units for sizes; `auto` picks the largest that keeps the value >= 1.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Unit {
+    Auto,
+    B,
+    K,
+    M,
+    G,
+    T,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StashMode {
+    /// Only when something is stashed, e.g. ≡2
+    Auto,
+    /// Also when the stash is empty, e.g. ≡0
+    Always,
+    Off,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AheadBehind {
+    /// Only the non-zero side(s), e.g. ↑2
+    Compact,
+    /// Both sides whenever an upstream exists, e.g. ↑2/↓0
+    Full,
+    Off,
+}
+
+/// What the time segment shows, before formatting.
+struct TimeData {
+    now: chrono::DateTime<Utc>,
+    /// None without --work-hours
+    hours: Option<clock::Hours>,
+    /// The nearest --until time or calendar event, and what it is
+    next: Option<(chrono::DateTime<Utc>, String)>,
+}
+
+fn collect_time(args: &TimeArgs) -> TimeData {
+    let now = Utc::now();
+    let local = now.with_timezone(&chrono::Local);
+    let hours = args.work_hours.map(|span| clock::hours(local, span, &args.work_days));
+    let times = args.until.iter()
+        .filter_map(|&t| clock::next_at(local, t))
+        .map(|at| (at.with_timezone(&Utc), at.format("%H:%M").to_string()));
+    let event = args.ics.as_deref()
+        .and_then(|p| ics::next_event(std::path::Path::new(p), now))
+        .map(|e| (e.start, e.summary));
+    let next = times.chain(event).min_by_key(|(at, _)| *at);
+    TimeData { now, hours, next }
+}
+
+fn render_time(args: &TimeArgs, d: &TimeData) -> String {
+    let now = d.now;
+    let fg = match d.hours {
+        Some(clock::Hours::Off) => Some(args.off_fg.as_str()),
+        Some(clock::Hours::DayOff) => Some(args.weekend_fg.as_deref().unwrap_or(&args.off_fg)),
+        _ => args.fg.as_deref(),
+    };
+    let s = if args.zones.is_empty() {
+        clock::Zone::local().format(now, &args.format)
+    } else {
+        let zones: Vec<String> = args.zones.iter().map(|z| render_zone(args, z, now, fg)).collect();
+        zones.join(&args.separator)
+    };
+    let countdown = countdown(args, d).unwrap_or_default();
+    // zones color themselves through --zone-template
+    let fg = match fg {
+        Some(c) if args.zones.is_empty() => tmux_fg(c),
+        _ => String::new(),
+    };
+    format!("{fg}{}{s}{countdown} ", args.icon)
+}
+
+// The nearest countdown target, rendered with --countdown-format.
+fn countdown(args: &TimeArgs, d: &TimeData) -> Option<String> {
+    let (at, what) = d.next.as_ref()?;
+    let left = (*at - d.now).to_std().ok()?;
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "in"   => fmt_uptime(left),
+            "what" => what.clone(),
+            _ => return None,
+        })
+    };
+    Some(template::render(&args.countdown_format, &lookup))
+}
+
+fn render_zone(args: &TimeArgs, zone: &clock::Zone, now: chrono::DateTime<Utc>, fg: Option<&str>) -> String {
+    let format = clock::lookup(&args.zone_format, &zone.label).unwrap_or(&args.format);
+    let fg = clock::lookup(&args.zone_fg, &zone.label).or(fg);
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            // reset, or the previous zone's color would carry over
+            "fg"    => tmux_fg(fg.unwrap_or("default")),
+            "icon"  => clock::lookup(&args.zone_icon, &zone.label).unwrap_or_default().to_string(),
+            "label" => zone.label.clone(),
+            "time"  => zone.format(now, format),
+            _ => return None,
+        })
+    };
+    template::render(&args.zone_template, &lookup)
+}
+
+fn json_time(args: &TimeArgs, d: &TimeData) -> json::Value {
+    let zones = if args.zones.is_empty() { vec![clock::Zone::local()] } else { args.zones.clone() };
+    let zones: Vec<json::Value> = zones.iter()
+        .map(|z| {
+            let format = clock::lookup(&args.zone_format, &z.label).unwrap_or(&args.format);
+            json::object([
+                ("label", z.label.as_str().into()),
+                ("zone", z.tz.map(|tz| tz.name()).into()),
+                ("time", z.format(d.now, "%Y-%m-%dT%H:%M:%S%:z").into()),
+                ("text", z.format(d.now, format).into()),
+            ])
+        })
+        .collect();
+    json::object([
+        ("unix", d.now.timestamp().into()),
+        ("zones", json::Value::Array(zones)),
+        ("hours", d.hours.map(clock::Hours::as_str).into()),
+        ("countdown", match &d.next {
+            Some((at, what)) => json::object([
+                ("at", at.timestamp().into()),
+                ("seconds", (*at - d.now).num_seconds().into()),
+                ("what", what.as_str().into()),
+            ]),
+            None => json::Value::Null,
+        }),
+    ])
+}
+
+// e.g. 2:05
+fn fmt_hm(d: Duration) -> String {
+    let m = d.as_secs() / 60;
+    format!("{}:{:02}", m / 60, m % 60)
+}
+
+// e.g. 90:00; a phase can run past an hour
+fn fmt_ms(d: Duration) -> String {
+    let s = d.as_secs();
+    format!("{}:{:02}", s / 60, s % 60)
+}
+
+fn render_battery(args: &BatteryArgs, b: &battery::Battery) -> String {
+    let charging = matches!(b.state, battery::State::Charging | battery::State::Full);
+
+    let level = if b.percent <= args.crit {
+        Level::Crit
+    } else if b.percent <= args.warn {
+        Level::Warn
+    } else {
+        Level::Ok
+    };
+    let fg = match &args.charging_fg {
+        Some(c) if charging => c.clone(),
+        _ if charging => args.colors.theme.palette().operation,
+        _ => args.colors.fg(level),
+    };
+    let icon = if charging {
+        args.charging_icon.clone()
+    } else if level == Level::Crit {
+        args.crit_icon.clone()
+    } else {
+        let i = (b.percent as usize * args.icons.len().saturating_sub(1) + 50) / 100;
+        args.icons.get(i).cloned().unwrap_or_default()
+    };
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"        => tmux_fg(&fg),
+            "icon"      => icon.clone(),
+            "percent"   => b.percent.to_string(),
+            "state"     => b.state.as_str().to_string(),
+            "remaining" => b.remaining.map(fmt_hm).unwrap_or_default(),
+            _ => return None,
+        })
+    };
+    template::render(&args.format, &lookup)
+}
+
+fn json_battery(b: &battery::Battery) -> json::Value {
+    json::object([
+        ("percent", b.percent.into()),
+        ("state", b.state.as_str().into()),
+        ("remaining", b.remaining.map(|d| d.as_secs()).into()),
+    ])
 }
 
-fn git_ok(path: &str, args: &[&str]) -> Option<String> {
-    let out = Command::new("git")
-        .args(["-C", path])
-        .args(args)
-        .output()
-        .ok()?;                    // could not spawn → None
-    if !out.status.success() {
-        return None;               // non-zero exit → None
+// Higher is worse, e.g. CPU or memory use.
+fn level_at_least(v: f64, warn: f64, crit: f64) -> Level {
+    if v >= crit {
+        Level::Crit
+    } else if v >= warn {
+        Level::Warn
+    } else {
+        Level::Ok
     }
-    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if s.is_empty() { None } else { Some(s) }
 }
 
-fn is_repo(path: &str) -> bool {
-    Command::new("git").args(["-C", path, "rev-parse", "--is-inside-working-tree"])
-    .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+// e.g. 3d 4h, 4h 12m, 12m
+fn fmt_uptime(d: Duration) -> String {
+    let m = d.as_secs() / 60;
+    let (days, hours, mins) = (m / 1440, m / 60 % 24, m % 60);
+    match (days, hours) {
+        (0, 0) => format!("{mins}m"),
+        (0, _) => format!("{hours}h {mins}m"),
+        _      => format!("{days}d {hours}h"),
+    }
 }
 
-fn repo_root_name(path: &str) -> Option<String> {
-    let root = git_ok(path, &["rev-parse", "--show-toplevel"])?;
-    Some(Path::new(&root).file_name()?.to_string_lossy().to_string())
+// One block character per value, scaled against `max`.
+fn sparkline(values: &[f64], max: f64) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    values.iter()
+        .map(|v| {
+            let i = if max > 0.0 { (v / max * 7.0).round() } else { 0.0 };
+            BARS[i.clamp(0.0, 7.0) as usize]
+        })
+        .collect()
 }
 
-fn head_name(path: &str) -> Option<String> {
-    if let Some(mut h) = git_ok(path, &["rev-parse", "--abbrev-ref", "HEAD"]) {
-        if h == "HEAD" {
-            if let Some(d) = git_ok(path, &["describe", "--contains", "--all", "HEAD"]) {
-                h = d;
+// e.g. 512K, 3.2G, 14G
+fn fmt_bytes(n: f64, unit: Unit) -> String {
+    const SUFFIX: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let exp = match unit {
+        Unit::Auto => {
+            let mut e = 0;
+            while e < 4 && n >= 1024f64.powi(e as i32 + 1) {
+                e += 1;
             }
+            e
         }
-        if !h.is_empty() {
-            return Some(h);
-        }
+        Unit::B => 0,
+        Unit::K => 1,
+        Unit::M => 2,
+        Unit::G => 3,
+        Unit::T => 4,
+    };
+    let v = n / 1024f64.powi(exp as i32);
+    if v < 10.0 && exp > 0 {
+        format!("{v:.1}{}", SUFFIX[exp])
+    } else {
+        format!("{v:.0}{}", SUFFIX[exp])
+    }
+}
+
+// e.g. ███░░░░░░░ for 30%
+fn bar(percent: f64, width: usize) -> String {
+    let full = ((percent / 100.0 * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(full), "░".repeat(width - full))
+}
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// CPU use in percent since the previous run.
+struct CpuData {
+    total: f64,
+    cores: Vec<f64>,
+    /// Recent totals, oldest first
+    history: Vec<f64>,
+}
+
+fn collect_cpu(args: &CpuArgs) -> Option<CpuData> {
+    let cur = cpu::Sample::read(std::path::Path::new(&args.proc_stat))?;
+
+    // the previous sample is stored as "<unix ms>\n<sample>"
+    let now = unix_ms();
+    let (then, prev) = cache::load("cpu-sample")
+        .and_then(|s| {
+            let (ts, rest) = s.split_once('\n')?;
+            Some((ts.parse().ok()?, cpu::Sample::decode(rest)))
+        })
+        .unwrap_or((0, cpu::Sample::default()));
+    let usage = cur.usage_since(&prev);
+    let total = usage[0];
+
+    let mut history: Vec<f64> = cache::load("cpu-history")
+        .map(|s| s.split_whitespace().filter_map(|v| v.parse().ok()).collect())
+        .unwrap_or_default();
+    // several clients refresh at once; keep the older sample until it is a
+    // second old so their deltas don't shrink to nothing
+    if now.saturating_sub(then) >= 1000 {
+        cache::store("cpu-sample", &format!("{now}\n{}", cur.encode()));
+        history.push(total);
+        let excess = history.len().saturating_sub(args.history);
+        history.drain(..excess);
+        let h: Vec<String> = history.iter().map(|v| format!("{v:.1}")).collect();
+        cache::store("cpu-history", &h.join(" "));
     }
 
-    None
+    Some(CpuData { total, cores: usage[1..].to_vec(), history })
 }
 
-fn repo_state(path: &str) -> &'static str {
-    // Run: git -C <path> status --porcelain
-    let out = match std::process::Command::new("git")
-        .args(["-C", path, "status", "--porcelain"])
-        .output()
-    {
-        Ok(o) => o,
-        Err(_) => return "clean", // if git can't run here, treat as clean/none
+fn render_cpu(args: &CpuArgs, d: &CpuData) -> String {
+    let total = d.total;
+    let max = d.cores.iter().copied().fold(total, f64::max);
+    let fg = args.colors.fg(level_at_least(total, args.warn as f64, args.crit as f64));
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"      => tmux_fg(&fg),
+            "icon"    => args.icon.clone(),
+            "percent" => format!("{total:.0}"),
+            "max"     => format!("{max:.0}"),
+            "cores"   => sparkline(&d.cores, 100.0),
+            "spark"   => sparkline(&d.history, 100.0),
+            _ => return None,
+        })
+    };
+    template::render(&args.format, &lookup)
+}
+
+fn json_cpu(d: &CpuData) -> json::Value {
+    json::object([
+        ("percent", d.total.into()),
+        ("cores", d.cores.clone().into()),
+        ("history", d.history.clone().into()),
+    ])
+}
+
+fn render_mem(args: &MemArgs, m: &mem::Mem) -> String {
+    let pct      = mem::percent(m.used(), m.total);
+    let swap_pct = mem::percent(m.swap_used(), m.swap_total);
+    let fg = args.colors.fg(level_at_least(pct, args.warn as f64, args.crit as f64));
+    let size = |n: u64| fmt_bytes(n as f64, args.unit);
+    // empty without swap, so `{?...}` sections around them vanish
+    let swap = |v: String| if m.swap_total == 0 { String::new() } else { v };
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"           => tmux_fg(&fg),
+            "icon"         => args.icon.clone(),
+            "used"         => size(m.used()),
+            "avail"        => size(m.available),
+            "total"        => size(m.total),
+            "percent"      => format!("{pct:.0}"),
+            "bar"          => bar(pct, args.bar_width),
+            "swap_used"    => swap(size(m.swap_used())),
+            "swap_total"   => swap(size(m.swap_total)),
+            "swap_percent" => swap(format!("{swap_pct:.0}")),
+            "swap_bar"     => swap(bar(swap_pct, args.bar_width)),
+            _ => return None,
+        })
+    };
+    template::render(&args.format, &lookup)
+}
+
+// Sizes in bytes.
+fn json_mem(m: &mem::Mem) -> json::Value {
+    json::object([
+        ("total", m.total.into()),
+        ("used", m.used().into()),
+        ("available", m.available.into()),
+        ("percent", mem::percent(m.used(), m.total).into()),
+        ("swap_total", m.swap_total.into()),
+        ("swap_used", m.swap_used().into()),
+        ("swap_percent", mem::percent(m.swap_used(), m.swap_total).into()),
+    ])
+}
+
+struct LoadData {
+    avg: [f64; 3],
+    cores: usize,
+    uptime: Option<Duration>,
+}
+
+fn collect_load(args: &LoadArgs) -> Option<LoadData> {
+    Some(LoadData {
+        avg: load::loadavg(std::path::Path::new(&args.loadavg))?,
+        cores: load::cores(),
+        uptime: load::uptime(std::path::Path::new(&args.uptime)),
+    })
+}
+
+fn render_load(args: &LoadArgs, d: &LoadData) -> String {
+    let (cores, uptime) = (d.cores, d.uptime);
+    let per_core = d.avg.map(|l| l / cores as f64);
+    let shown = if args.normalize { per_core } else { d.avg };
+    let fg = args.colors.fg(level_at_least(per_core[0], args.warn, args.crit));
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"     => tmux_fg(&fg),
+            "icon"   => args.icon.clone(),
+            "load1"  => format!("{:.2}", shown[0]),
+            "load5"  => format!("{:.2}", shown[1]),
+            "load15" => format!("{:.2}", shown[2]),
+            "cores"  => cores.to_string(),
+            "uptime" => uptime.map(fmt_uptime).unwrap_or_default(),
+            _ => return None,
+        })
     };
+    template::render(&args.format, &lookup)
+}
+
+fn json_load(d: &LoadData) -> json::Value {
+    json::object([
+        ("load", d.avg.to_vec().into()),
+        ("cores", d.cores.into()),
+        ("uptime", d.uptime.map(|u| u.as_secs()).into()),
+    ])
+}
+
+/// Totals and rates in bytes, summed over the selected interfaces.
+struct NetData {
+    ifaces: Vec<String>,
+    rx: u64,
+    tx: u64,
+    rx_rate: f64,
+    tx_rate: f64,
+    up: bool,
+}
+
+fn collect_net(args: &NetArgs) -> Option<NetData> {
+    use std::path::Path;
+    let (sysfs, dev) = (Path::new(&args.sysfs), Path::new(&args.proc_dev));
+    let ifaces: Vec<String> = match args.iface.as_str() {
+        "default" => net::default_iface(Path::new(&args.proc_route)).into_iter().collect(),
+        glob if glob.contains(['*', '?']) => net::interfaces(sysfs, dev)
+            .into_iter()
+            .filter(|n| net::glob_match(glob, n))
+            .collect(),
+        name => vec![name.to_string()],
+    };
+    let counters: Vec<(u64, u64)> = ifaces.iter().filter_map(|i| net::counters(sysfs, dev, i)).collect();
+    if counters.is_empty() {
+        return None;
+    }
+    let rx: u64 = counters.iter().map(|c| c.0).sum();
+    let tx: u64 = counters.iter().map(|c| c.1).sum();
+    let up = ifaces.iter().any(|i| net::is_up(sysfs, i));
 
-    let s = String::from_utf8_lossy(&out.stdout);
-    if s.lines().any(|l| matches!(l.get(0..2), Some("UU" | "AA" | "DD" | "AU" | "UD" | "UA" | "DU"))) {
-        return "conflict";
+    // previous counters stored as "<unix ms> <rx> <tx>", one entry per selection
+    let name = format!("net-{}", cache::key(&ifaces));
+    let now = unix_ms();
+    let prev: Option<(u64, u64, u64)> = cache::load(&name).and_then(|s| {
+        let mut f = s.split_whitespace().map(|v| v.parse().ok());
+        Some((f.next()??, f.next()??, f.next()??))
+    });
+    let (rx_rate, tx_rate) = match prev {
+        Some((then, prx, ptx)) if now > then => {
+            let dt = (now - then) as f64 / 1000.0;
+            // counters reset when an interface goes away and comes back
+            (rx.saturating_sub(prx) as f64 / dt, tx.saturating_sub(ptx) as f64 / dt)
+        }
+        _ => (0.0, 0.0),
+    };
+    // same as the cpu segment: don't let concurrent clients shrink the window
+    if prev.is_none_or(|(then, _, _)| now.saturating_sub(then) >= 1000) {
+        cache::store(&name, &format!("{now} {rx} {tx}"));
     }
-    if s.lines().any(|l| l.starts_with("??")) {
-        return "untracked";
+    Some(NetData { ifaces, rx, tx, rx_rate, tx_rate, up })
+}
+
+fn render_net(args: &NetArgs, d: &NetData) -> String {
+    let up = d.up;
+    let fg = args.colors.fg(if up { Level::Ok } else { Level::Crit });
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"    => tmux_fg(&fg),
+            "icon"  => if up { args.icon.clone() } else { args.down_icon.clone() },
+            "iface" => d.ifaces.join(","),
+            "rx"    => format!("{}/s", fmt_bytes(d.rx_rate, args.unit)),
+            "tx"    => format!("{}/s", fmt_bytes(d.tx_rate, args.unit)),
+            "state" => if up { "up".into() } else { "down".into() },
+            _ => return None,
+        })
+    };
+    template::render(&args.format, &lookup)
+}
+
+fn json_net(d: &NetData) -> json::Value {
+    json::object([
+        ("ifaces", d.ifaces.clone().into()),
+        ("up", d.up.into()),
+        ("rx", d.rx.into()),
+        ("tx", d.tx.into()),
+        ("rx_rate", d.rx_rate.into()),
+        ("tx_rate", d.tx_rate.into()),
+    ])
+}
+
+// Each --path that could be read, with its mount point.
+fn collect_disk(args: &DiskArgs) -> Option<Vec<(std::path::PathBuf, disk::Usage)>> {
+    let mut paths = args.paths.clone();
+    if paths.is_empty() {
+        let pane = tmux::pane_path(tmux::Target {
+            pane: args.pane.as_deref(),
+            client: args.client.as_deref(),
+        });
+        paths.push(pane.unwrap_or_else(|| ".".into()));
     }
-    if s.lines().any(|l| l.chars().next().map(|c| "MRADC".contains(c)).unwrap_or(false)) {
-        return "staged";
+    let found = paths.iter()
+        .filter_map(|p| {
+            let path = std::path::Path::new(p);
+            let u = disk::usage(path)?;
+            Some((disk::mount_point(path).unwrap_or_else(|| path.to_path_buf()), u))
+        })
+        .collect();
+    Some(found)
+}
+
+fn render_disk(args: &DiskArgs, disks: &[(std::path::PathBuf, disk::Usage)]) -> String {
+    let mut parts = Vec::new();
+    for (mount, u) in disks {
+        let pct = u.percent();
+        let fg = args.colors.fg(level_at_least(pct, args.warn as f64, args.crit as f64));
+        let size = |n: u64| fmt_bytes(n as f64, args.unit);
+
+        let lookup = |key: &str| -> Option<String> {
+            Some(match key {
+                "fg"      => tmux_fg(&fg),
+                "icon"    => args.icon.clone(),
+                "mount"   => mount.to_string_lossy().to_string(),
+                "free"    => size(u.avail),
+                "used"    => size(u.used),
+                "total"   => size(u.total),
+                "percent" => format!("{pct:.0}"),
+                "bar"     => bar(pct, args.bar_width),
+                _ => return None,
+            })
+        };
+        parts.push(template::render(&args.format, &lookup));
     }
-    if s.lines().any(|l| l.chars().nth(1).map(|c| "MRADC D".contains(c)).unwrap_or(false)) {
-        return "unstaged";
+    parts.join(&args.separator)
+}
+
+fn json_disk(disks: &[(std::path::PathBuf, disk::Usage)]) -> json::Value {
+    let disks: Vec<json::Value> = disks.iter()
+        .map(|(mount, u)| json::object([
+            ("mount", mount.to_string_lossy().to_string().into()),
+            ("total", u.total.into()),
+            ("used", u.used.into()),
+            ("free", u.avail.into()),
+            ("percent", u.percent().into()),
+        ]))
+        .collect();
+    json::Value::Array(disks)
+}
+
+fn run_timer(cmd: &TimerCmd) -> std::io::Result<()> {
+    let now = unix_ms();
+    match cmd {
+        TimerCmd::Start(a) => {
+            let running = timer::update(|t| {
+                let lengths = timer::Lengths {
+                    work: a.work * 60,
+                    short: a.short_break * 60,
+                    long: a.long_break * 60,
+                    rounds: a.rounds,
+                };
+                let running = matches!(t, Some(t) if matches!(t.run, timer::Run::Running { .. }));
+                let mut t = t.unwrap_or_else(|| timer::Timer::start(lengths, now));
+                t.resume(now);
+                (Some(t), running)
+            })?;
+            if running {
+                return Err(std::io::Error::other("a timer is already running; `tmuxstar timer stop` it first"));
+            }
+            Ok(())
+        }
+        TimerCmd::Pause => timer::update(|t| {
+            (t.map(|mut t| { t.pause(now); t }), ())
+        }),
+        TimerCmd::Stop => timer::update(|_| (None, ())),
+        TimerCmd::Status(_) => unreachable!("rendered as a segment"),
     }
-    "clean"
 }
 
-fn state_color_fg(state: &str) -> &'static str {
-    match state {
-        "conflict" | "unstaged" => "#ff6b6b",
-        "staged"                => "#f1fa8c",
-        "untracked"             => "#bd93f9",
-        "clean"                 => "#50fa7b",
-        _                       => "white",
+// Advances the stored timer past finished phases, running --on-end. None
+// when the timer is stopped; otherwise the timer and the time it was read.
+fn collect_timer(args: &TimerStatusArgs) -> Option<(timer::Timer, u64)> {
+    let now = unix_ms();
+    let read = timer::update(|t| {
+        let Some(mut t) = t else { return (None, (None, None)); };
+        let ended = t.advance(now);
+        (Some(t), (Some(t), ended))
+    });
+    let (t, ended) = match read {
+        Ok((t, ended)) => (t?, ended),
+        Err(e) => {
+            eprintln!("tmuxstar timer: {e}");
+            return None;
+        }
+    };
+    if let (Some(ended), Some(hook)) = (ended, &args.on_end) {
+        let _ = std::process::Command::new("sh")
+            .args(["-c", hook])
+            .env("TMUXSTAR_PHASE", ended.as_str())
+            .env("TMUXSTAR_NEXT", t.phase.as_str())
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .spawn();
     }
+    Some((t, now))
+}
+
+fn render_timer(args: &TimerStatusArgs, (t, now): &(timer::Timer, u64)) -> String {
+    let now = *now;
+    let palette = args.theme.palette();
+    let paused = matches!(t.run, timer::Run::Paused { .. });
+    let (fg, icon) = match t.phase {
+        _ if paused => (args.paused_fg.clone().unwrap_or(palette.operation), &args.paused_icon),
+        timer::Phase::Work => (args.work_fg.clone().unwrap_or(palette.conflict), &args.work_icon),
+        _ => (args.break_fg.clone().unwrap_or(palette.clean), &args.break_icon),
+    };
+    let left = t.left(now);
+    // same chrono formatting as the time segment
+    let ends = chrono::DateTime::from_timestamp_millis((now + left) as i64)
+        .filter(|_| !paused)
+        .map(|at| clock::Zone::local().format(at, &args.ends_format))
+        .unwrap_or_default();
+    let left = fmt_ms(Duration::from_millis(left.div_ceil(1000) * 1000));
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "fg"    => tmux_fg(&fg),
+            "icon"  => icon.clone(),
+            "phase" => t.phase.as_str().to_string(),
+            "left"  => left.clone(),
+            "ends"  => ends.clone(),
+            "round" => t.round.to_string(),
+            _ => return None,
+        })
+    };
+    template::render(&args.format, &lookup)
+}
+
+fn json_timer((t, now): &(timer::Timer, u64)) -> json::Value {
+    let left = t.left(*now);
+    let paused = matches!(t.run, timer::Run::Paused { .. });
+    json::object([
+        ("phase", t.phase.as_str().into()),
+        ("round", t.round.into()),
+        ("paused", paused.into()),
+        ("left", left.div_ceil(1000).into()),
+        ("ends", (!paused).then(|| (now + left) / 1000).into()),
+    ])
 }
 
 fn tmux_fg(color: &str) -> String {
     format!("#[fg={}]", color)
 }
 
-fn print_git(path: &str, label_fg: &str, icon: &str) {
-    if !is_repo(path) {
-        return;
+fn tmux_bg(color: &str) -> String {
+    format!("#[bg={}]", color)
+}
+
+// Paints a segment's --bg behind it and restores the status background after.
+fn with_bg(out: String, bg: Option<&str>) -> String {
+    match bg {
+        Some(c) if !out.is_empty() => format!("{}{out}{}", tmux_bg(c), tmux_bg("default")),
+        _ => out,
+    }
+}
+
+fn fmt_ahead_behind(args: &GitArgs, upstream: Option<(usize, usize)>) -> String {
+    let Some((ahead, behind)) = upstream else { return String::new(); };
+    let up   = format!("{}{ahead}", args.ahead_icon);
+    let down = format!("{}{behind}", args.behind_icon);
+    match args.ahead_behind {
+        AheadBehind::Off => String::new(),
+        AheadBehind::Full => format!("{up}/{down}"),
+        AheadBehind::Compact => match (ahead, behind) {
+            (0, 0) => String::new(),
+            (_, 0) => up,
+            (0, _) => down,
+            _      => format!("{up}/{down}"),
+        },
+    }
+}
+
+fn fmt_stash(args: &GitArgs, stash: usize) -> String {
+    match args.stash {
+        StashMode::Off => String::new(),
+        StashMode::Auto if stash == 0 => String::new(),
+        _ => format!("{}{stash}", args.stash_icon),
+    }
+}
+
+fn fmt_counts(counts: &[Change], status: &Status) -> String {
+    counts.iter()
+        .filter(|&&c| status.count(c) > 0)
+        .map(|&c| format!("{}{}", c.symbol(), status.count(c)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Zero counts render empty so `{?...}` sections around them disappear.
+fn nonzero(n: usize) -> String {
+    if n == 0 { String::new() } else { n.to_string() }
+}
+
+fn render_git(info: &RepoInfo, args: &GitArgs) -> String {
+    let state  = info.status.state();
+    let palette = args.palette();
+    let c_icon = palette.state(state); // hex like "#50fa7b"
+    let (ahead, behind) = info.upstream.unwrap_or((0, 0));
+
+    let lookup = |key: &str| -> Option<String> {
+        Some(match key {
+            "state_fg"   => tmux_fg(c_icon),
+            "label_fg"   => tmux_fg(&args.label_fg),
+            "op_fg"      => tmux_fg(&palette.operation),
+            "stash_fg"   => tmux_fg(&palette.stash),
+            "op"         => info.operation.as_ref().map(|o| o.label()).unwrap_or_default(),
+            "icon"       => args.icon.clone(),
+            "project"    => info.project.clone(),
+            "branch"     => info.branch.clone(),
+            "state"      => state.to_string(),
+            "root"       => info.root.to_string_lossy().to_string(),
+            "tag"        => info.tag.clone().unwrap_or_default(),
+            "stash"      => nonzero(info.stash),
+            "stashed"    => fmt_stash(args, info.stash),
+            "ahead"      => nonzero(ahead),
+            "behind"     => nonzero(behind),
+            "ab"         => fmt_ahead_behind(args, info.upstream),
+            "counts"     => fmt_counts(&args.counts, &info.status),
+            "staged"     => nonzero(info.status.staged),
+            "modified"   => nonzero(info.status.modified),
+            "deleted"    => nonzero(info.status.deleted),
+            "renamed"    => nonzero(info.status.renamed),
+            "untracked"  => nonzero(info.status.untracked),
+            "conflicted" => nonzero(info.status.conflicted),
+            _ => return None,
+        })
+    };
+
+    template::render(&args.format, &lookup)
+}
+
+fn json_git(info: &RepoInfo) -> json::Value {
+    let s = &info.status;
+    json::object([
+        ("project", info.project.as_str().into()),
+        ("branch", info.branch.as_str().into()),
+        ("state", s.state().into()),
+        ("root", info.root.to_string_lossy().to_string().into()),
+        ("tag", info.tag.clone().into()),
+        ("stash", info.stash.into()),
+        ("upstream", match info.upstream {
+            Some((ahead, behind)) => json::object([("ahead", ahead.into()), ("behind", behind.into())]),
+            None => json::Value::Null,
+        }),
+        ("operation", match &info.operation {
+            Some(op) => json::object([
+                ("name", op.name.into()),
+                ("progress", op.progress.map(|(done, total)| vec![done, total]).into()),
+                ("branch", op.branch.clone().into()),
+            ]),
+            None => json::Value::Null,
+        }),
+        ("counts", json::object([
+            ("staged", s.staged.into()),
+            ("modified", s.modified.into()),
+            ("deleted", s.deleted.into()),
+            ("renamed", s.renamed.into()),
+            ("untracked", s.untracked.into()),
+            ("conflicted", s.conflicted.into()),
+        ])),
+    ])
+}
+
+// `stale_key` names the cache entry holding the last output for this exact
+// invocation, shown again (marked) when git doesn't answer in time.
+fn read_git(path: &str, args: &GitArgs, json: bool, stale_key: &str, started: Instant) -> String {
+    let info = if args.timeout == 0 {
+        git::read(path, args.backend, args.extras(json))
+    } else {
+        // --timeout counts from `started`, before asking the daemon
+        let left = Duration::from_millis(args.timeout).saturating_sub(started.elapsed());
+        let read = match left.is_zero() {
+            true => Err(git::TimedOut),
+            false => git::read_within(path, args.backend, args.extras(json), left),
+        };
+        match read {
+            Ok(info) => info,
+            Err(git::TimedOut) if json => return json::object([("timed_out", true.into())]).to_string(),
+            Err(git::TimedOut) => {
+                return match cache::load(stale_key) {
+                    Some(last) => format!("{}{last}", args.stale_mark),
+                    None => args.placeholder.clone(),
+                };
+            }
+        }
+    };
+    if json {
+        return json::Value::from(info.as_ref().map(json_git)).to_string();
+    }
+    let Some(info) = info else { return String::new(); };
+    let out = render_git(&info, args);
+    cache::store(stale_key, &out);
+    out
+}
+
+// `--json` prints the collected data itself, `null` when there is none.
+fn emit<T>(data: Option<T>, json: bool, to_json: impl FnOnce(&T) -> json::Value, render: impl FnOnce(&T) -> String) -> String {
+    match data {
+        Some(d) if json => to_json(&d).to_string(),
+        Some(d) => render(&d),
+        None if json => json::Value::Null.to_string(),
+        None => String::new(),
+    }
+}
+
+// One line for a clap error about segment command line `s`.
+fn segment_error(s: &str, e: &clap::Error) -> String {
+    let msg = e.to_string();
+    let first = msg.lines().next().unwrap_or_default();
+    format!("`{s}`: {}", first.trim_start_matches("error: "))
+}
+
+// A segment command line with what `install` adds by default: the theme,
+// and for git and disk the pane tmux is drawing the status line for.
+fn install_segment(s: &str, theme: Theme) -> Result<String, String> {
+    let words = status::split(s)?;
+    let mut argv = vec!["tmuxstar".to_string()];
+    argv.extend(words.iter().cloned());
+    let cli = Cli::try_parse_from(&argv).map_err(|e| segment_error(s, &e))?;
+    // tmux expands the line before running it
+    let mut words: Vec<String> = words.iter().map(|w| install::format_escape(w)).collect();
+
+    let mut cmd = Cli::command();
+    for w in &words {
+        match cmd.find_subcommand(w) {
+            Some(sub) => cmd = sub.clone(),
+            None => break,
+        }
+    }
+    let given = |flag: &str| words.iter().any(|w| w == flag || w.starts_with(&format!("{flag}=")));
+    let (has_theme, has_path) = (given("--theme"), given("--path") || given("--pane") || given("--client"));
+    if cmd.get_arguments().any(|a| a.get_id() == "theme") && !has_theme {
+        let name = theme.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+        words.extend(["--theme".to_string(), name]);
+    }
+    if matches!(cli.cmd, Cmd::Git(_) | Cmd::Disk(_))
+        && !has_path
+    {
+        words.extend(["--pane".to_string(), "#{pane_id}".to_string()]);
+    }
+    Ok(words.iter().map(|w| status::quote(w)).collect::<Vec<_>>().join(" "))
+}
+
+fn install_settings(args: &InstallArgs) -> Result<Vec<(&'static str, String)>, String> {
+    let bin = args.bin.clone()
+        .or_else(|| std::env::current_exe().ok().map(|p| p.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "tmuxstar".into());
+    let side = |segments: &[String], side: &str| -> Result<String, String> {
+        let mut cmd = vec![install::sh_quote(&install::format_escape(&bin)), "status".into()];
+        for s in segments {
+            cmd.push("--segment".into());
+            cmd.push(install::sh_quote(&install_segment(s, args.theme)?));
+        }
+        if let Some(p) = args.powerline {
+            let name = p.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+            cmd.extend(["--powerline".into(), name, "--side".into(), side.into()]);
+        }
+        Ok(install::tmux_quote(&format!("#({})", cmd.join(" "))))
+    };
+
+    let mut settings = vec![("status-interval", args.interval.to_string())];
+    if !args.left.is_empty() {
+        settings.push(("status-left-length", args.length.to_string()));
+        settings.push(("status-left", side(&args.left, "left")?));
+    }
+    if !args.right.is_empty() {
+        settings.push(("status-right-length", args.length.to_string()));
+        settings.push(("status-right", side(&args.right, "right")?));
+    }
+    Ok(settings)
+}
+
+fn run_install(args: &InstallArgs) -> Result<(), String> {
+    let block = install::block(&install_settings(args)?);
+    if args.print {
+        print!("{block}");
+        return Ok(());
+    }
+    let conf = args.file.clone().map(Into::into).or_else(install::default_conf).ok_or("no home directory")?;
+    match install::write(&conf, Some(&block)).map_err(|e| format!("{}: {e}", conf.display()))? {
+        install::Written::Unchanged => println!("{} is up to date", conf.display()),
+        install::Written::Created => println!("created {}", conf.display()),
+        install::Written::Updated(backup) => {
+            println!("updated {}", conf.display());
+            if let Some(b) = backup {
+                println!("previous version saved as {}", b.display());
+            }
+        }
     }
-    let Some(project) = repo_root_name(path) else { return; };
-    let Some(branch)  = head_name(path)      else { return; };
+    if tmux::run(&["source-file", &conf.to_string_lossy()]) {
+        println!("reloaded tmux");
+    }
+    Ok(())
+}
 
-    let state  = repo_state(path);
-    let c_icon = state_color_fg(state); // hex like "#50fa7b"
+fn run_uninstall(file: Option<String>) -> Result<(), String> {
+    let conf = file.map(Into::into).or_else(install::default_conf).ok_or("no home directory")?;
+    let install::Written::Updated(backup) = install::write(&conf, None).map_err(|e| format!("{}: {e}", conf.display()))? else {
+        println!("nothing to remove from {}", conf.display());
+        return Ok(());
+    };
+    println!("updated {}", conf.display());
+    if let Some(b) = backup {
+        println!("previous version saved as {}", b.display());
+    }
+    // sourcing the file again doesn't unset what the block had set
+    let unset = ["status-interval", "status-left", "status-left-length", "status-right", "status-right-length"];
+    if unset.iter().all(|o| tmux::run(&["set", "-gu", o])) && tmux::run(&["source-file", &conf.to_string_lossy()]) {
+        println!("reloaded tmux");
+    }
+    Ok(())
+}
 
-    let out = format!(
-        "{icon_col}{icon}{restore}{project}({branch}) ",
-        icon_col = tmux_fg(c_icon),
-        icon     = icon,
-        restore  = tmux_fg(label_fg),
-        project  = project,
-        branch   = branch,
-    );
+impl Cmd {
+    /// The --bg of a segment command.
+    fn bg(&self) -> Option<&str> {
+        let b = match self {
+            Cmd::Git(a)     => &a.background,
+            Cmd::Time(a)    => &a.background,
+            Cmd::Battery(a) => &a.background,
+            Cmd::Cpu(a)     => &a.background,
+            Cmd::Mem(a)     => &a.background,
+            Cmd::Load(a)    => &a.background,
+            Cmd::Net(a)     => &a.background,
+            Cmd::Disk(a)    => &a.background,
+            Cmd::Timer { cmd: TimerCmd::Status(a) } => &a.background,
+            _ => return None,
+        };
+        b.bg.as_deref()
+    }
+}
 
-    println!("{out}");
+/// What a segment command shows (or its data as JSON), without a trailing
+/// newline; None for commands that aren't segments. `argv` is the
+/// (config-merged) command line that produced `cmd`.
+fn segment(cmd: &Cmd, json: bool, argv: &[OsString]) -> Option<String> {
+    Some(match cmd {
+        Cmd::Git(args) => {
+            let started = Instant::now();
+            // `#()` commands run in the tmux server's cwd, not the pane's
+            let pane = match &args.path {
+                Some(_) => None,
+                None => tmux::pane_path(tmux::Target {
+                    pane: args.pane.as_deref(),
+                    client: args.client.as_deref(),
+                }),
+            };
+            let mut query: Vec<OsString> = argv[1..].to_vec();
+            query.extend(pane.as_ref().map(|p| format!("--path={p}").into()));
+            if !args.no_daemon && let Some(out) = daemon::query(&query, args.timeout) {
+                return Some(out.trim_end_matches('\n').to_string());
+            }
+            let p = args.path.clone().or(pane).unwrap_or_else(|| ".".into());
+            let key = format!("git-{}", cache::key((std::env::current_dir().ok(), &query)));
+            read_git(&p, args, json, &key, started)
+        }
+        Cmd::Time(args) => {
+            emit(Some(collect_time(args)), json, |d| json_time(args, d), |d| render_time(args, d))
+        }
+        Cmd::Battery(args) => {
+            let b = battery::read(std::path::Path::new(&args.sysfs), args.name.as_deref());
+            emit(b, json, json_battery, |b| render_battery(args, b))
+        }
+        Cmd::Cpu(args) => emit(collect_cpu(args), json, json_cpu, |d| render_cpu(args, d)),
+        Cmd::Mem(args) => {
+            let m = mem::Mem::read(std::path::Path::new(&args.meminfo));
+            emit(m, json, json_mem, |m| render_mem(args, m))
+        }
+        Cmd::Load(args) => emit(collect_load(args), json, json_load, |d| render_load(args, d)),
+        Cmd::Net(args)  => emit(collect_net(args), json, json_net, |d| render_net(args, d)),
+        Cmd::Disk(args) => emit(collect_disk(args), json, |d| json_disk(d), |d| render_disk(args, d)),
+        Cmd::Timer { cmd: TimerCmd::Status(args) } => {
+            emit(collect_timer(args), json, json_timer, |t| render_timer(args, t))
+        }
+        _ => return None,
+    })
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let argv = config::merged_args(Cli::command(), std::env::args_os().collect());
+    let cli = Cli::parse_from(&argv);
     match cli.cmd {
-        Cmd::Git { path, label_fg, icon } => {
-            let p = path.unwrap_or_else(|| ".".into());
-            print_git(&p, &label_fg, &icon);
+        Cmd::Status(args) => {
+            match status::render(cli.config.as_deref(), cli.profile.as_deref(), &args, cli.json) {
+                Ok(out) if cli.json => println!("{out}"),
+                Ok(out) => println!("{}", cli.output.render(&out)),
+                Err(e) => {
+                    eprintln!("tmuxstar status: {e}");
+                    std::process::exit(2);
+                }
+            }
+        }
+        Cmd::Timer { cmd } if !matches!(cmd, TimerCmd::Status(_)) => {
+            if let Err(e) = run_timer(&cmd) {
+                eprintln!("tmuxstar timer: {e}");
+                std::process::exit(1);
+            }
         }
-        Cmd::Time { format, icon } => {
-            print_time(&format, &icon);
+        Cmd::Install(args) => {
+            if let Err(e) = run_install(&args) {
+                eprintln!("tmuxstar install: {e}");
+                std::process::exit(1);
+            }
+        }
+        Cmd::Uninstall { file } => {
+            if let Err(e) = run_uninstall(file) {
+                eprintln!("tmuxstar uninstall: {e}");
+                std::process::exit(1);
+            }
+        }
+        Cmd::Daemon { socket, ttl } => {
+            let socket = socket.map(Into::into).unwrap_or_else(daemon::socket_path);
+            if let Err(e) = daemon::run(&socket, Duration::from_secs(ttl)) {
+                eprintln!("tmuxstar daemon: {}: {e}", socket.display());
+                std::process::exit(1);
+            }
+        }
+        cmd => {
+            let out = segment(&cmd, cli.json, &argv).unwrap_or_default();
+            if cli.json {
+                println!("{out}");
+            } else if let Cmd::Time(_) = cmd
+                && cli.output == output::Output::Tmux
+            {
+                // kept without a newline for existing tmux setups
+                print!("{}", with_bg(out, cmd.bg()));
+            } else if !out.is_empty() {
+                println!("{}", cli.output.render(&with_bg(out, cmd.bg())));
+            }
         }
     }
 }
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+pub const PROC_MEMINFO: &str = "/proc/meminfo";
+
+/// Sizes in bytes.
+pub struct Mem {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+impl Mem {
+    pub fn read(path: &Path) -> Option<Mem> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let field = |name: &str| -> Option<u64> {
+            let line = text.lines().find(|l| l.split(':').next() == Some(name))?;
+            let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+            Some(kb * 1024)
+        };
+        let total = field("MemTotal")?;
+        // MemAvailable appeared in 3.14; older kernels only have MemFree
+        let available = field("MemAvailable").or_else(|| field("MemFree"))?;
+        Some(Mem {
+            total,
+            available,
+            swap_total: field("SwapTotal").unwrap_or(0),
+            swap_free: field("SwapFree").unwrap_or(0),
+        })
+    }
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    pub fn swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+}
+
+pub fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 * 100.0 / whole as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn reads_meminfo() {
+        let text = "MemTotal:        8000 kB\nMemFree:         1000 kB\nMemAvailable:    3000 kB\n\
+                    SwapTotal:       2000 kB\nSwapFree:        1500 kB\n";
+        let f = Fixture::new("mem-meminfo", &[("meminfo", text)]);
+        let m = Mem::read(&f.path().join("meminfo")).unwrap();
+        assert_eq!((m.total, m.used(), m.swap_used()), (8000 * 1024, 5000 * 1024, 500 * 1024));
+    }
+
+    #[test]
+    fn old_kernels_without_memavailable() {
+        // and no swap lines at all
+        let text = "MemTotal:        8000 kB\nMemFree:         1000 kB\n";
+        let f = Fixture::new("mem-old", &[("meminfo", text)]);
+        let m = Mem::read(&f.path().join("meminfo")).unwrap();
+        assert_eq!((m.used(), m.swap_total, m.swap_used()), (7000 * 1024, 0, 0));
+        assert_eq!(percent(m.swap_used(), m.swap_total), 0.0);
+    }
+
+    #[test]
+    fn missing_total() {
+        let f = Fixture::new("mem-bad", &[("meminfo", "MemFree: 1000 kB\n")]);
+        assert!(Mem::read(&f.path().join("meminfo")).is_none());
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::Path;
+
+pub const SYSFS: &str = "/sys/class/net";
+pub const PROC_ROUTE: &str = "/proc/net/route";
+pub const PROC_DEV: &str = "/proc/net/dev";
+
+/// The interface carrying the IPv4 default route with the lowest metric.
+pub fn default_iface(route: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(route).ok()?;
+    text.lines()
+        .skip(1)
+        .filter_map(|l| {
+            // Iface Destination Gateway Flags RefCnt Use Metric ...
+            let f: Vec<&str> = l.split_whitespace().collect();
+            let up = u32::from_str_radix(f.get(3)?, 16).ok()? & 1 == 1;
+            if f.get(1) != Some(&"00000000") || !up {
+                return None;
+            }
+            Some((f.get(6)?.parse::<u64>().ok()?, f[0].to_string()))
+        })
+        .min()
+        .map(|(_, iface)| iface)
+}
+
+pub fn interfaces(sysfs: &Path, dev: &Path) -> Vec<String> {
+    let mut names: Vec<String> = match std::fs::read_dir(sysfs) {
+        Ok(rd) => rd.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect(),
+        Err(_) => proc_dev(dev).into_iter().map(|(n, _, _)| n).collect(),
+    };
+    names.sort();
+    names
+}
+
+/// Total (rx, tx) bytes, from sysfs with /proc/net/dev as a fallback.
+pub fn counters(sysfs: &Path, dev: &Path, iface: &str) -> Option<(u64, u64)> {
+    let stat = |f: &str| -> Option<u64> {
+        let p = sysfs.join(iface).join("statistics").join(f);
+        std::fs::read_to_string(p).ok()?.trim().parse().ok()
+    };
+    if let (Some(rx), Some(tx)) = (stat("rx_bytes"), stat("tx_bytes")) {
+        return Some((rx, tx));
+    }
+    proc_dev(dev).into_iter().find(|(n, _, _)| n == iface).map(|(_, rx, tx)| (rx, tx))
+}
+
+pub fn is_up(sysfs: &Path, iface: &str) -> bool {
+    let read = |f: &str| std::fs::read_to_string(sysfs.join(iface).join(f)).unwrap_or_default();
+    match read("operstate").trim() {
+        "up" => true,
+        // loopback, tun and friends don't report a state; trust the carrier
+        "unknown" => read("carrier").trim() == "1",
+        _ => false,
+    }
+}
+
+// name, rx bytes, tx bytes
+fn proc_dev(dev: &Path) -> Vec<(String, u64, u64)> {
+    let Ok(text) = std::fs::read_to_string(dev) else { return Vec::new(); };
+    text.lines()
+        .skip(2)
+        .filter_map(|l| {
+            let (name, rest) = l.split_once(':')?;
+            let f: Vec<u64> = rest.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+            Some((name.trim().to_string(), *f.first()?, *f.get(8)?))
+        })
+        .collect()
+}
+
+/// Shell-style match supporting `*` and `?`.
+pub fn glob_match(pat: &str, s: &str) -> bool {
+    let (p, s): (Vec<char>, Vec<char>) = (pat.chars().collect(), s.chars().collect());
+    let (mut pi, mut si) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while si < s.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == s[si]) {
+            pi += 1;
+            si += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, si));
+            pi += 1;
+        } else if let Some((sp, ss)) = star {
+            // let the last `*` swallow one more char and retry
+            pi = sp + 1;
+            si = ss + 1;
+            star = Some((sp, ss + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    const ROUTE: &str = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t00000000\t0100000A\t0003\t0\t0\t100\t00000000\t0\t0\t0
+wg0\t00000000\t00000000\t0000\t0\t0\t50\t00000000\t0\t0\t0
+eth0\t0000000A\t00000000\t0001\t0\t0\t10\t00FFFFFF\t0\t0\t0
+";
+
+    const DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+ eth0: 5000000    4000    0    0    0     0          0         0   700000    3000    0    0    0     0       0          0
+";
+
+    #[test]
+    fn default_route_with_lowest_metric() {
+        // wg0 has a lower metric but its route is down; the 10/8 route
+        // isn't a default one
+        let f = Fixture::new("net-route", &[("route", ROUTE)]);
+        assert_eq!(default_iface(&f.path().join("route")).as_deref(), Some("eth0"));
+
+        let f = Fixture::new("net-route-none", &[("route", ROUTE.lines().next().unwrap())]);
+        assert_eq!(default_iface(&f.path().join("route")), None);
+    }
+
+    #[test]
+    fn counters_from_sysfs_then_proc_dev() {
+        let f = Fixture::new("net-counters", &[
+            ("sys/wlan0/statistics/rx_bytes", "123\n"),
+            ("sys/wlan0/statistics/tx_bytes", "456\n"),
+            ("sys/wlan0/operstate", "up\n"),
+            ("sys/tun0/operstate", "unknown\n"),
+            ("sys/tun0/carrier", "1\n"),
+            ("sys/eth1/operstate", "down\n"),
+            ("dev", DEV),
+        ]);
+        let (sys, dev) = (f.path().join("sys"), f.path().join("dev"));
+        assert_eq!(counters(&sys, &dev, "wlan0"), Some((123, 456)));
+        assert_eq!(counters(&sys, &dev, "eth0"), Some((5000000, 700000)));
+        assert_eq!(counters(&sys, &dev, "ppp0"), None);
+
+        assert!(is_up(&sys, "wlan0"));
+        assert!(is_up(&sys, "tun0"));
+        assert!(!is_up(&sys, "eth1"));
+        assert!(!is_up(&sys, "ppp0"));
+
+        assert_eq!(interfaces(&sys, &dev), ["eth1", "tun0", "wlan0"]);
+        assert_eq!(interfaces(&f.path().join("nosys"), &dev), ["eth0", "lo"]);
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("wl*", "wlan0"));
+        assert!(glob_match("eth?", "eth0"));
+        assert!(glob_match("*n*0", "wlan0"));
+        assert!(glob_match("veth*", "veth"));
+        assert!(!glob_match("eth?", "eth10"));
+        assert!(!glob_match("wl*", "eth0"));
+        assert!(!glob_match("", "lo"));
+        assert!(!glob_match("*x", "lo"));
+    }
+}
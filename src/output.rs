@@ -0,0 +1,408 @@
+// Output backends. Segments style their text with tmux markup
+// (`#[fg=red,bg=#282a36]`), which doubles as the internal format: every
+// other backend parses it back into colored spans and re-emits those.
+
+use crate::json;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// tmux `#[fg=...]` markup
+    Tmux,
+    /// ANSI escapes with 24-bit color
+    Ansi,
+    /// ANSI escapes limited to the 256-color palette
+    Ansi256,
+    /// ANSI escapes limited to the 16 basic colors
+    Ansi16,
+    /// zsh prompt `%F{...}`/`%K{...}`, for assigning PROMPT from a precmd
+    /// hook with PROMPT_SUBST set: `PROMPT="$(tmuxstar --output zsh ...)"`
+    Zsh,
+    /// bash prompt escapes wrapped in `\[...\]`, for assigning PS1 from
+    /// PROMPT_COMMAND: `PS1="$(tmuxstar --output bash ...)"`
+    Bash,
+    /// One i3bar/swaybar status line: a JSON array of blocks, one per color
+    /// run, without the protocol header or the enclosing endless array
+    I3bar,
+    /// A waybar custom module object with Pango markup
+    Waybar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Color {
+    Default,
+    /// 0-7 normal, 8-15 bright
+    Basic(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+const BASIC: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+// xterm's defaults for the 16 basic colors
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+impl Color {
+    // The color names tmux accepts: default, black..white, brightred,
+    // colour0..colour255 and #rrggbb.
+    fn parse(s: &str) -> Option<Color> {
+        let s = s.trim().to_ascii_lowercase();
+        if s == "default" || s == "terminal" {
+            return Some(Color::Default);
+        }
+        if let Some(hex) = s.strip_prefix('#') {
+            let v = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)?;
+            return Some(Color::Rgb((v >> 16) as u8, (v >> 8) as u8, v as u8));
+        }
+        if let Some(n) = s.strip_prefix("colour").or_else(|| s.strip_prefix("color")) {
+            return n.parse().ok().map(Color::Indexed);
+        }
+        let (name, bright) = match s.strip_prefix("bright") {
+            Some(n) => (n, 8),
+            None => (s.as_str(), 0),
+        };
+        BASIC.iter().position(|&b| b == name).map(|i| Color::Basic(i as u8 + bright))
+    }
+
+    fn rgb(self) -> Option<(u8, u8, u8)> {
+        Some(match self {
+            Color::Default => return None,
+            Color::Basic(i) => BASIC_RGB[i as usize],
+            Color::Indexed(i) if i < 16 => BASIC_RGB[i as usize],
+            Color::Indexed(i) if i >= 232 => {
+                let v = 8 + (i - 232) * 10;
+                (v, v, v)
+            }
+            Color::Indexed(i) => {
+                let level = |c: u8| if c == 0 { 0 } else { 55 + c * 40 };
+                let i = i - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        })
+    }
+
+    fn hex(self) -> Option<String> {
+        self.rgb().map(|(r, g, b)| format!("#{r:02x}{g:02x}{b:02x}"))
+    }
+
+    // Nearest entry of the 6x6x6 cube or grayscale ramp.
+    fn to_256(self) -> Color {
+        let Color::Rgb(r, g, b) = self else { return self; };
+        let cube = |c: u8| if c < 48 { 0 } else if c < 115 { 1 } else { (c - 35) / 40 };
+        let cube_i = 16 + 36 * cube(r) + 6 * cube(g) + cube(b);
+        let avg = (r as u16 + g as u16 + b as u16) / 3;
+        let gray_i = if avg < 8 { 232 } else { (232 + (avg - 8) / 10).min(255) as u8 };
+        let (c, g2) = (Color::Indexed(cube_i), Color::Indexed(gray_i));
+        if distance(self, g2) < distance(self, c) { g2 } else { c }
+    }
+
+    fn to_16(self) -> Color {
+        match self {
+            Color::Default | Color::Basic(_) => self,
+            _ => (0..16).map(Color::Basic).min_by_key(|&c| distance(self, c)).unwrap_or(self),
+        }
+    }
+
+    // SGR parameters for this color as foreground (or background).
+    fn sgr(self, bg: bool) -> String {
+        let base = if bg { 40 } else { 30 };
+        match self {
+            Color::Default => (base + 9).to_string(),
+            Color::Basic(i) if i < 8 => (base + i as u16).to_string(),
+            Color::Basic(i) => (base + 60 + i as u16 - 8).to_string(),
+            Color::Indexed(i) => format!("{};5;{i}", base + 8),
+            Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+        }
+    }
+
+    fn zsh(self) -> Option<String> {
+        match self {
+            Color::Default => None,
+            Color::Basic(i) | Color::Indexed(i) => Some(i.to_string()),
+            Color::Rgb(..) => self.hex(),
+        }
+    }
+}
+
+fn distance(a: Color, b: Color) -> u32 {
+    let (Some(a), Some(b)) = (a.rgb(), b.rgb()) else { return u32::MAX; };
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// A run of text with the colors in effect for it.
+struct Span {
+    fg: Color,
+    bg: Color,
+    text: String,
+}
+
+// Splits tmux markup into spans; attributes other than fg/bg are dropped.
+fn spans(markup: &str) -> Vec<Span> {
+    let mut out: Vec<Span> = Vec::new();
+    let (mut fg, mut bg) = (Color::Default, Color::Default);
+    let mut rest = markup;
+    loop {
+        let (text, style) = match rest.find("#[") {
+            Some(i) => match rest[i..].find(']') {
+                Some(j) => (&rest[..i], Some(&rest[i + 2..i + j])),
+                None => (rest, None),
+            },
+            None => (rest, None),
+        };
+        if !text.is_empty() {
+            match out.last_mut() {
+                Some(s) if s.fg == fg && s.bg == bg => s.text.push_str(text),
+                _ => out.push(Span { fg, bg, text: text.to_string() }),
+            }
+        }
+        let Some(style) = style else { break; };
+        rest = &rest[text.len() + style.len() + 3..];
+        for attr in style.split([',', ' ']).filter(|a| !a.is_empty()) {
+            match attr.split_once('=') {
+                Some(("fg", c)) => fg = Color::parse(c).unwrap_or(fg),
+                Some(("bg", c)) => bg = Color::parse(c).unwrap_or(bg),
+                None if attr == "default" => (fg, bg) = (Color::Default, Color::Default),
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+impl Output {
+    /// Re-renders tmux-markup `text` for this backend.
+    pub fn render(self, text: &str) -> String {
+        match self {
+            Output::Tmux    => text.to_string(),
+            Output::Ansi    => ansi(text, |c| c),
+            Output::Ansi256 => ansi(text, Color::to_256),
+            Output::Ansi16  => ansi(text, Color::to_16),
+            Output::Zsh     => zsh(text),
+            Output::Bash    => bash(text),
+            Output::I3bar   => i3bar(text),
+            Output::Waybar  => waybar(text),
+        }
+    }
+}
+
+fn ansi(text: &str, depth: fn(Color) -> Color) -> String {
+    let spans = spans(text);
+    let mut out = String::new();
+    for s in &spans {
+        out.push_str(&format!("\x1b[{};{}m{}", depth(s.fg).sgr(false), depth(s.bg).sgr(true), s.text));
+    }
+    if !spans.is_empty() {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+fn zsh(text: &str) -> String {
+    let mut out = String::new();
+    for s in spans(text) {
+        match s.fg.zsh() {
+            Some(c) => out.push_str(&format!("%F{{{c}}}")),
+            None => out.push_str("%f"),
+        }
+        match s.bg.zsh() {
+            Some(c) => out.push_str(&format!("%K{{{c}}}")),
+            None => out.push_str("%k"),
+        }
+        out.push_str(&zsh_escape(&s.text));
+    }
+    if !out.is_empty() {
+        out.push_str("%f%k");
+    }
+    out
+}
+
+fn bash(text: &str) -> String {
+    let spans = spans(text);
+    let mut out = String::new();
+    for s in &spans {
+        out.push_str(&format!("\\[\\e[{};{}m\\]{}", s.fg.sgr(false), s.bg.sgr(true), bash_escape(&s.text)));
+    }
+    if !spans.is_empty() {
+        out.push_str("\\[\\e[0m\\]");
+    }
+    out
+}
+
+// With PROMPT_SUBST the prompt is expanded like a double-quoted string,
+// then `%` sequences are; text must come through both literally. Inside
+// `PROMPT='$(tmuxstar ...)'` the output is not expanded again, so the
+// backslashes would show: the precmd assignment is the supported setup.
+fn zsh_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '%' => out.push_str("%%"),
+            '$' | '`' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// bash decodes prompt escapes (`\\` is one backslash) before expanding the
+// result like a double-quoted string (promptvars), so each needs two rounds.
+fn bash_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\\\\\"),
+            '$' | '`' => {
+                out.push_str("\\\\");
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// A single line of the i3bar protocol; the status_command wrapping tmuxstar
+// prints `{"version":1}` and `[` itself, then each line followed by a comma.
+fn i3bar(text: &str) -> String {
+    let blocks: Vec<String> = spans(text)
+        .iter()
+        .map(|s| {
+            let mut b = format!("{{\"full_text\":{}", json::string(&s.text));
+            if let Some(c) = s.fg.hex() {
+                b.push_str(&format!(",\"color\":\"{c}\""));
+            }
+            if let Some(c) = s.bg.hex() {
+                b.push_str(&format!(",\"background\":\"{c}\""));
+            }
+            // runs of one segment must not be pulled apart
+            b.push_str(",\"separator\":false,\"separator_block_width\":0}");
+            b
+        })
+        .collect();
+    format!("[{}]", blocks.join(","))
+}
+
+fn waybar(text: &str) -> String {
+    let spans = spans(text);
+    let mut markup = String::new();
+    let mut plain = String::new();
+    for s in &spans {
+        let escaped = s.text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let mut attrs = String::new();
+        if let Some(c) = s.fg.hex() {
+            attrs.push_str(&format!(" foreground=\"{c}\""));
+        }
+        if let Some(c) = s.bg.hex() {
+            attrs.push_str(&format!(" background=\"{c}\""));
+        }
+        if attrs.is_empty() {
+            markup.push_str(&escaped);
+        } else {
+            markup.push_str(&format!("<span{attrs}>{escaped}</span>"));
+        }
+        plain.push_str(&s.text);
+    }
+    format!("{{\"text\":{},\"tooltip\":{}}}", json::string(markup.trim_end()), json::string(plain.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tmux_color_names() {
+        assert_eq!(Color::parse("default"), Some(Color::Default));
+        assert_eq!(Color::parse("Terminal"), Some(Color::Default));
+        assert_eq!(Color::parse("red"), Some(Color::Basic(1)));
+        assert_eq!(Color::parse("brightblue"), Some(Color::Basic(12)));
+        assert_eq!(Color::parse("colour235"), Some(Color::Indexed(235)));
+        assert_eq!(Color::parse("color7"), Some(Color::Indexed(7)));
+        assert_eq!(Color::parse("#D08770"), Some(Color::Rgb(0xd0, 0x87, 0x70)));
+        for bad in ["#fff", "#gggggg", "colour256", "brightdefault", "orange", ""] {
+            assert_eq!(Color::parse(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn reduces_to_the_nearest_palette_entry() {
+        assert_eq!(Color::Rgb(255, 0, 0).to_256(), Color::Indexed(196));
+        assert_eq!(Color::Rgb(0x5f, 0x87, 0xaf).to_256(), Color::Indexed(67));
+        // a dark blue-gray is closer to the grayscale ramp than to the cube
+        assert_eq!(Color::Rgb(0x28, 0x2a, 0x36).to_256(), Color::Indexed(235));
+        assert_eq!(Color::Basic(3).to_256(), Color::Basic(3));
+        assert_eq!(Color::Default.to_256(), Color::Default);
+
+        assert_eq!(Color::Rgb(255, 0, 0).to_16(), Color::Basic(9));
+        assert_eq!(Color::Rgb(0xcd, 0, 0).to_16(), Color::Basic(1));
+        assert_eq!(Color::Indexed(4).to_16(), Color::Basic(4));
+        assert_eq!(Color::Indexed(231).to_16(), Color::Basic(15));
+        assert_eq!(Color::Default.to_16(), Color::Default);
+    }
+
+    fn texts(markup: &str) -> Vec<(Color, Color, String)> {
+        spans(markup).into_iter().map(|s| (s.fg, s.bg, s.text)).collect()
+    }
+
+    #[test]
+    fn splits_markup_into_spans() {
+        use Color::*;
+        let t = |s: &str| s.to_string();
+        assert_eq!(
+            texts("a#[fg=red]b#[bg=colour4,bold] c#[fg=red]d#[default]e#[fg=blue]"),
+            [
+                (Default, Default, t("a")),
+                (Basic(1), Default, t("b")),
+                // a style that changes nothing continues the run
+                (Basic(1), Indexed(4), t(" cd")),
+                (Default, Default, t("e")),
+            ]
+        );
+        assert_eq!(texts("#[fg=nonsense]x#[unterminated"), [(Default, Default, t("x#[unterminated"))]);
+        assert!(texts("").is_empty());
+    }
+
+    #[test]
+    fn ansi_escapes() {
+        let red = "#[fg=#ff0000,bg=default]x";
+        assert_eq!(Output::Ansi.render(red), "\x1b[38;2;255;0;0;49mx\x1b[0m");
+        assert_eq!(Output::Ansi256.render(red), "\x1b[38;5;196;49mx\x1b[0m");
+        assert_eq!(Output::Ansi16.render(red), "\x1b[91;49mx\x1b[0m");
+        assert_eq!(Output::Ansi.render(""), "");
+    }
+
+    #[test]
+    fn zsh_escapes_for_a_precmd_assignment() {
+        assert_eq!(Output::Zsh.render("#[fg=red]100% $HOME `id` \\"), "%F{1}%k100%% \\$HOME \\`id\\` \\\\%f%k");
+        assert_eq!(Output::Zsh.render("#[bg=#282a36]x"), "%f%K{#282a36}x%f%k");
+    }
+
+    #[test]
+    fn bash_escapes_for_a_prompt_command_assignment() {
+        assert_eq!(
+            Output::Bash.render("#[fg=colour9]$HOME `id` \\"),
+            "\\[\\e[38;5;9;49m\\]\\\\$HOME \\\\`id\\\\` \\\\\\\\\\[\\e[0m\\]"
+        );
+    }
+
+    #[test]
+    fn bar_escapes() {
+        assert_eq!(
+            Output::I3bar.render("#[fg=red]\"a\"\\"),
+            "[{\"full_text\":\"\\\"a\\\"\\\\\",\"color\":\"#cd0000\",\"separator\":false,\"separator_block_width\":0}]"
+        );
+        assert_eq!(
+            Output::Waybar.render("#[bg=blue]<a&b>"),
+            "{\"text\":\"<span background=\\\"#0000ee\\\">&lt;a&amp;b&gt;</span>\",\"tooltip\":\"<a&b>\"}"
+        );
+    }
+}
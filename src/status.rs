@@ -0,0 +1,239 @@
+// `tmuxstar status`: several segments rendered by one process.
+//
+// Each `--segment` is a command line like `git --icon x`, split with shell
+// quoting rules, merged with the config file like a standalone invocation
+// and rendered on its own thread.
+
+use crate::{Cli, StatusArgs, config, segment, segment_error, with_bg};
+use clap::{CommandFactory, Parser, ValueEnum};
+use std::ffi::OsString;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Powerline {
+    Arrow,
+    Rounded,
+    Slanted,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Powerline {
+    // (solid, thin) glyphs from the Powerline/Nerd Font private use area
+    fn glyphs(self, side: Side) -> (char, char) {
+        match (self, side) {
+            (Powerline::Arrow,   Side::Left)  => ('\u{e0b0}', '\u{e0b1}'),
+            (Powerline::Arrow,   Side::Right) => ('\u{e0b2}', '\u{e0b3}'),
+            (Powerline::Rounded, Side::Left)  => ('\u{e0b4}', '\u{e0b5}'),
+            (Powerline::Rounded, Side::Right) => ('\u{e0b6}', '\u{e0b7}'),
+            (Powerline::Slanted, Side::Left)  => ('\u{e0bc}', '\u{e0bd}'),
+            (Powerline::Slanted, Side::Right) => ('\u{e0ba}', '\u{e0bb}'),
+        }
+    }
+}
+
+/// The segments joined into one line, or with `json` a JSON array of
+/// `{"segment": name, "data": ...}` objects.
+pub fn render(config: Option<&str>, profile: Option<&str>, args: &StatusArgs, json: bool) -> Result<String, String> {
+    if args.segments.is_empty() {
+        return Err("no segments: pass --segment or set `segment` under [status] in the config".into());
+    }
+    let mut argvs = Vec::new();
+    for s in &args.segments {
+        let mut argv: Vec<OsString> = vec!["tmuxstar".into()];
+        if let Some(c) = config {
+            argv.extend(["--config".into(), c.into()]);
+        }
+        if let Some(p) = profile {
+            argv.extend(["--profile".into(), p.into()]);
+        }
+        argv.extend(split(s)?.into_iter().map(OsString::from));
+        let argv = config::merged_args(Cli::command(), argv);
+        let cli = Cli::try_parse_from(&argv).map_err(|e| segment_error(s, &e))?;
+        argvs.push((cli, argv));
+    }
+
+    let parts: Vec<Option<String>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = argvs.iter()
+            .map(|(cli, argv)| scope.spawn(|| segment(&cli.cmd, json, argv)))
+            .collect();
+        handles.into_iter().map(|h| h.join().ok().flatten()).collect()
+    });
+    let mut shown = Vec::new();
+    let mut data = Vec::new();
+    for ((part, (cli, _)), s) in parts.into_iter().zip(&argvs).zip(&args.segments) {
+        let Some(part) = part else { return Err(format!("`{s}` is not a segment")); };
+        if json {
+            let name = s.split_whitespace().next().unwrap_or_default();
+            data.push(format!("{{\"segment\":{},\"data\":{part}}}", crate::json::string(name)));
+            continue;
+        }
+        // segments end in padding of their own; the separator replaces it
+        let text = part.trim_end().to_string();
+        if !text.is_empty() {
+            shown.push((text, cli.cmd.bg()));
+        }
+    }
+
+    if json {
+        return Ok(format!("[{}]", data.join(",")));
+    }
+    Ok(match args.powerline {
+        Some(style) => powerline(&shown, style, args.side, &args.status_bg),
+        None => {
+            let parts: Vec<String> = shown.into_iter().map(|(t, bg)| with_bg(t, bg)).collect();
+            parts.join(&args.separator)
+        }
+    })
+}
+
+// Each glyph is drawn in the color of the segment it points out of, over
+// the background of the one it points into. Segments without --bg sit on
+// the status line's own background.
+fn powerline(parts: &[(String, Option<&str>)], style: Powerline, side: Side, status_bg: &str) -> String {
+    let (solid, thin) = style.glyphs(side);
+    let glyph = |from: &str, to: &str| {
+        let (fg, bg) = if side == Side::Left { (from, to) } else { (to, from) };
+        if from == to {
+            thin.to_string()
+        } else if fg == "default" {
+            // fg=default is the status text color, not its background: the
+            // glyph would be a block of the wrong color, so the change of
+            // background alone marks the edge
+            String::new()
+        } else {
+            format!("#[fg={fg},bg={bg}]{solid}")
+        }
+    };
+
+    let mut out = String::new();
+    let mut prev = status_bg;
+    for (i, (text, bg)) in parts.iter().enumerate() {
+        let bg = bg.unwrap_or(status_bg);
+        // no glyph at the outer edge when there's no color to blend
+        if i > 0 || (side == Side::Right && bg != status_bg) {
+            out.push_str(&glyph(prev, bg));
+        }
+        // the glyph's fg would otherwise carry over into uncolored text
+        out.push_str(&format!("#[fg=default,bg={bg}] {text} "));
+        prev = bg;
+    }
+    if side == Side::Left && prev != status_bg {
+        out.push_str(&glyph(prev, status_bg));
+    }
+    out
+}
+
+/// `word` quoted so that `split` gives it back unchanged.
+pub fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c)) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+// Splits a command line into words: whitespace separates, single quotes are
+// literal, double quotes and backslashes escape like in sh.
+pub fn split(s: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let w = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err(format!("unterminated quote in `{s}`")),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => w.extend(chars.next()),
+                        Some(c) => w.push(c),
+                        None => return Err(format!("unterminated quote in `{s}`")),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_default().extend(chars.next()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Vec<String> {
+        split(s).unwrap()
+    }
+
+    #[test]
+    fn split_like_sh() {
+        assert_eq!(words("  git   --icon x "), ["git", "--icon", "x"]);
+        assert_eq!(words(r#"time --format '%H:%M {zone}'"#), ["time", "--format", "%H:%M {zone}"]);
+        assert_eq!(words(r#"a "b \"c\" d" e\ f"#), ["a", r#"b "c" d"#, "e f"]);
+        assert_eq!(words(r"'it'\''s' ''"), ["it's", ""]);
+        assert_eq!(words(r"'\n' \\"), [r"\n", r"\"]);
+        assert!(words("").is_empty());
+    }
+
+    #[test]
+    fn split_rejects_unterminated_quotes() {
+        assert!(split("git --icon 'x").is_err());
+        assert!(split(r#"git --icon "x\""#).is_err());
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        for w in ["git", "", "%H:%M {zone}", "it's", r#"a "b" \c"#, "#{pane_id}", "tab\there"] {
+            assert_eq!(words(&quote(w)), [w], "{w:?} quoted as {}", quote(w));
+        }
+        assert_eq!(quote("--theme"), "--theme");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+
+    fn parts<'a>(bgs: &[Option<&'a str>]) -> Vec<(String, Option<&'a str>)> {
+        bgs.iter().enumerate().map(|(i, &bg)| (format!("s{i}"), bg)).collect()
+    }
+
+    #[test]
+    fn powerline_never_draws_in_the_text_color() {
+        let (solid, _) = Powerline::Arrow.glyphs(Side::Left);
+        let out = powerline(&parts(&[Some("red"), None, Some("blue")]), Powerline::Arrow, Side::Left, "default");
+        assert_eq!(out, format!(
+            "#[fg=default,bg=red] s0 #[fg=red,bg=default]{solid}#[fg=default,bg=default] s1 \
+             #[fg=default,bg=blue] s2 #[fg=blue,bg=default]{solid}"
+        ));
+
+        let (solid, _) = Powerline::Arrow.glyphs(Side::Right);
+        let out = powerline(&parts(&[None, Some("red"), None]), Powerline::Arrow, Side::Right, "default");
+        assert_eq!(out, format!(
+            "#[fg=default,bg=default] s0 #[fg=red,bg=default]{solid}#[fg=default,bg=red] s1 \
+             #[fg=default,bg=default] s2 "
+        ));
+    }
+
+    #[test]
+    fn powerline_blends_concrete_colors() {
+        let (solid, thin) = Powerline::Arrow.glyphs(Side::Right);
+        let out = powerline(&parts(&[Some("red"), None, None]), Powerline::Arrow, Side::Right, "#282a36");
+        assert_eq!(out, format!(
+            "#[fg=red,bg=#282a36]{solid}#[fg=default,bg=red] s0 #[fg=#282a36,bg=red]{solid}\
+             #[fg=default,bg=#282a36] s1 {thin}#[fg=default,bg=#282a36] s2 "
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+// Tiny format-string engine for segment templates.
+//
+//   {name}       replaced by the value of `name`
+//   {?...}       conditional section, dropped if any placeholder inside is empty
+//   {{ and }}    literal braces
+//
+// Unknown placeholders are left as-is so typos show up in the status line.
+
+pub fn render(tpl: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    render_section(tpl, lookup).0
+}
+
+// Returns the rendered text and whether every placeholder in it had a value.
+fn render_section(tpl: &str, lookup: &dyn Fn(&str) -> Option<String>) -> (String, bool) {
+    let mut out = String::new();
+    let mut complete = true;
+    let mut rest = tpl;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix('}') {
+            out.push('}');
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = closing_brace(tail) else {
+            // unbalanced: keep the remainder verbatim
+            out.push_str(tail);
+            return (out, complete);
+        };
+        let inner = &tail[1..end];
+        rest = &tail[end + 1..];
+
+        if let Some(section) = inner.strip_prefix('?') {
+            let (s, ok) = render_section(section, lookup);
+            if ok {
+                out.push_str(&s);
+            }
+            continue;
+        }
+        match lookup(inner) {
+            Some(v) => {
+                if v.is_empty() {
+                    complete = false;
+                }
+                out.push_str(&v);
+            }
+            None => {
+                out.push('{');
+                out.push_str(inner);
+                out.push('}');
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, complete)
+}
+
+// Index of the `}` matching the `{` at the start of `s`.
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(tpl: &str) -> String {
+        render(tpl, &|name| match name {
+            "branch" => Some("main".into()),
+            "ahead" => Some("2".into()),
+            "behind" => Some(String::new()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(r("{branch} ↑{ahead}"), "main ↑2");
+        assert_eq!(r("[{behind}]"), "[]");
+        assert_eq!(r("{typo}"), "{typo}");
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(r("{branch}{? ↑{ahead}}"), "main ↑2");
+        assert_eq!(r("{branch}{? ↓{behind}}"), "main");
+        // unknown names don't count as empty
+        assert_eq!(r("{? {typo}}"), " {typo}");
+        // an inner section dropping doesn't take the outer one with it
+        assert_eq!(r("{?<{ahead}{? ↓{behind}}>}"), "<2>");
+        assert_eq!(r("{?<{behind}{? ↑{ahead}}>}"), "");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(r("{{branch}}"), "{branch}");
+        assert_eq!(r("}} {{"), "} {");
+        assert_eq!(r("{?{{{ahead}}}}"), "{2}");
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert_eq!(r("{branch"), "{branch");
+        assert_eq!(r("x {? {ahead}"), "x {? {ahead}");
+        assert_eq!(r("a } b"), "a } b");
+    }
+}
@@ -0,0 +1,105 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Theme {
+    Dracula,
+    Nord,
+    Gruvbox,
+    Solarized,
+    Catppuccin,
+}
+
+/// Colors for each git working-tree state.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub conflict: String,
+    pub unstaged: String,
+    pub staged: String,
+    pub untracked: String,
+    pub clean: String,
+    /// In-progress rebase/merge/cherry-pick/...
+    pub operation: String,
+    /// Stash entry count
+    pub stash: String,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        let (conflict, unstaged, staged, untracked, clean, operation, stash) = match self {
+            Theme::Dracula    => ("#ff6b6b", "#ff6b6b", "#f1fa8c", "#bd93f9", "#50fa7b", "#ffb86c", "#8be9fd"),
+            Theme::Nord       => ("#bf616a", "#d08770", "#ebcb8b", "#b48ead", "#a3be8c", "#88c0d0", "#81a1c1"),
+            Theme::Gruvbox    => ("#fb4934", "#fe8019", "#fabd2f", "#d3869b", "#b8bb26", "#83a598", "#8ec07c"),
+            Theme::Solarized  => ("#dc322f", "#cb4b16", "#b58900", "#6c71c4", "#859900", "#2aa198", "#268bd2"),
+            Theme::Catppuccin => ("#f38ba8", "#fab387", "#f9e2af", "#cba6f7", "#a6e3a1", "#89b4fa", "#94e2d5"),
+        };
+        Palette {
+            conflict:  conflict.into(),
+            unstaged:  unstaged.into(),
+            staged:    staged.into(),
+            untracked: untracked.into(),
+            clean:     clean.into(),
+            operation: operation.into(),
+            stash:     stash.into(),
+        }
+    }
+}
+
+/// How worrying a gauge reading is; segments map their thresholds onto this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Ok,
+    Warn,
+    Crit,
+}
+
+impl Palette {
+    pub fn level(&self, level: Level) -> &str {
+        match level {
+            Level::Ok   => &self.clean,
+            Level::Warn => &self.staged,
+            Level::Crit => &self.conflict,
+        }
+    }
+
+    pub fn state(&self, state: &str) -> &str {
+        match state {
+            "conflict"  => &self.conflict,
+            "unstaged"  => &self.unstaged,
+            "staged"    => &self.staged,
+            "untracked" => &self.untracked,
+            "clean"     => &self.clean,
+            _           => "white",
+        }
+    }
+}
+
+/// Theme and color overrides shared by the gauge-style segments.
+#[derive(clap::Args)]
+pub struct LevelColors {
+    #[arg(long, value_enum, default_value="dracula")]
+    pub theme: Theme,
+    #[arg(long)]
+    pub ok_fg: Option<String>,
+    #[arg(long)]
+    pub warn_fg: Option<String>,
+    #[arg(long)]
+    pub crit_fg: Option<String>,
+}
+
+impl LevelColors {
+    pub fn fg(&self, level: Level) -> String {
+        let over = match level {
+            Level::Ok   => &self.ok_fg,
+            Level::Warn => &self.warn_fg,
+            Level::Crit => &self.crit_fg,
+        };
+        over.clone().unwrap_or_else(|| self.theme.palette().level(level).to_string())
+    }
+}
+
+/// Segment background; `status --powerline` blends it into the separators.
+#[derive(clap::Args)]
+pub struct Background {
+    #[arg(long)]
+    pub bg: Option<String>,
+}
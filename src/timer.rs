@@ -0,0 +1,258 @@
+// Pomodoro timer state, kept in $XDG_STATE_HOME/tmuxstar/timer so every
+// `tmuxstar timer ...` call and status refresh sees the same clock.
+//
+// The file holds one `key value` pair per line; times are unix ms and
+// lengths are seconds. No file means the timer is stopped.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Work      => "work",
+            Phase::Break     => "break",
+            Phase::LongBreak => "long-break",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Phase> {
+        Some(match s {
+            "work"       => Phase::Work,
+            "break"      => Phase::Break,
+            "long-break" => Phase::LongBreak,
+            _ => return None,
+        })
+    }
+}
+
+/// Phase lengths in seconds, fixed when the timer is started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lengths {
+    pub work: u64,
+    pub short: u64,
+    pub long: u64,
+    /// Work phases before a long break
+    pub rounds: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Run {
+    Running { ends: u64 },
+    Paused { left: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timer {
+    pub phase: Phase,
+    /// 1-based count of the current work phase within a set
+    pub round: u32,
+    pub run: Run,
+    pub lengths: Lengths,
+}
+
+impl Timer {
+    pub fn start(lengths: Lengths, now: u64) -> Timer {
+        Timer {
+            phase: Phase::Work,
+            round: 1,
+            run: Run::Running { ends: now + lengths.work * 1000 },
+            lengths,
+        }
+    }
+
+    /// Milliseconds left in the current phase.
+    pub fn left(&self, now: u64) -> u64 {
+        match self.run {
+            Run::Running { ends } => ends.saturating_sub(now),
+            Run::Paused { left } => left,
+        }
+    }
+
+    pub fn pause(&mut self, now: u64) {
+        if let Run::Running { .. } = self.run {
+            self.run = Run::Paused { left: self.left(now) };
+        }
+    }
+
+    pub fn resume(&mut self, now: u64) {
+        if let Run::Paused { left } = self.run {
+            self.run = Run::Running { ends: now + left };
+        }
+    }
+
+    /// Moves past every phase that has ended by `now`, returning the last
+    /// one that ended.
+    pub fn advance(&mut self, now: u64) -> Option<Phase> {
+        let mut ended = None;
+        while let Run::Running { ends } = self.run {
+            if ends > now {
+                break;
+            }
+            ended = Some(self.phase);
+            let (phase, round) = match self.phase {
+                Phase::Work if self.round >= self.lengths.rounds => (Phase::LongBreak, self.round),
+                Phase::Work => (Phase::Break, self.round),
+                Phase::Break => (Phase::Work, self.round + 1),
+                Phase::LongBreak => (Phase::Work, 1),
+            };
+            let secs = match phase {
+                Phase::Work      => self.lengths.work,
+                Phase::Break     => self.lengths.short,
+                Phase::LongBreak => self.lengths.long,
+            };
+            self.phase = phase;
+            self.round = round;
+            // a zero-length phase would never end
+            self.run = Run::Running { ends: ends + secs.max(1) * 1000 };
+        }
+        ended
+    }
+
+    fn encode(&self) -> String {
+        let run = match self.run {
+            Run::Running { ends } => format!("ends {ends}"),
+            Run::Paused { left } => format!("left {left}"),
+        };
+        let l = &self.lengths;
+        format!(
+            "phase {}\nround {}\n{run}\nwork {}\nbreak {}\nlong-break {}\nrounds {}\n",
+            self.phase.as_str(), self.round, l.work, l.short, l.long, l.rounds,
+        )
+    }
+
+    fn decode(text: &str) -> Option<Timer> {
+        let kv: HashMap<&str, &str> = text.lines().filter_map(|l| l.split_once(' ')).collect();
+        let num = |k: &str| kv.get(k)?.parse::<u64>().ok();
+        let run = match (num("ends"), num("left")) {
+            (Some(ends), _) => Run::Running { ends },
+            (None, Some(left)) => Run::Paused { left },
+            _ => return None,
+        };
+        Some(Timer {
+            phase: Phase::parse(kv.get("phase")?)?,
+            round: num("round")? as u32,
+            run,
+            lengths: Lengths {
+                work: num("work")?,
+                short: num("break")?,
+                long: num("long-break")?,
+                rounds: num("rounds")? as u32,
+            },
+        })
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("state"),
+    };
+    Some(base.join("tmuxstar").join("timer"))
+}
+
+/// Runs `f` on the stored timer (None when stopped) and stores what it
+/// returns, holding a lock so concurrent status refreshes advance a phase
+/// only once. Returns `f`'s second value.
+pub fn update<T>(f: impl FnOnce(Option<Timer>) -> (Option<Timer>, T)) -> std::io::Result<T> {
+    let path = path().ok_or_else(|| std::io::Error::other("no home directory"))?;
+    let dir = path.parent().expect("state path has a parent");
+    std::fs::create_dir_all(dir)?;
+    let lock = std::fs::File::create(dir.join("timer.lock"))?;
+    // released when `lock` is dropped
+    lock.lock()?;
+
+    let before = std::fs::read_to_string(&path).ok().and_then(|t| Timer::decode(&t));
+    let (after, out) = f(before);
+    if after == before {
+        return Ok(out);
+    }
+    match after {
+        Some(t) => {
+            let tmp = path.with_extension(std::process::id().to_string());
+            std::fs::write(&tmp, t.encode())?;
+            std::fs::rename(&tmp, &path)?;
+        }
+        None => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        },
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTHS: Lengths = Lengths { work: 10, short: 2, long: 5, rounds: 2 };
+
+    #[test]
+    fn phases_in_order() {
+        // work 0-10s, break -12s, work -22s, long break -27s, work again
+        let mut t = Timer::start(LENGTHS, 0);
+        assert_eq!(t.advance(9_999), None);
+        assert_eq!(t.advance(10_000), Some(Phase::Work));
+        assert_eq!((t.phase, t.round, t.left(10_000)), (Phase::Break, 1, 2_000));
+        assert_eq!(t.advance(12_000), Some(Phase::Break));
+        assert_eq!((t.phase, t.round), (Phase::Work, 2));
+        assert_eq!(t.advance(22_500), Some(Phase::Work));
+        assert_eq!((t.phase, t.round, t.left(22_500)), (Phase::LongBreak, 2, 4_500));
+        assert_eq!(t.advance(27_000), Some(Phase::LongBreak));
+        assert_eq!((t.phase, t.round, t.run), (Phase::Work, 1, Run::Running { ends: 37_000 }));
+    }
+
+    #[test]
+    fn catches_up_on_missed_phases() {
+        let mut t = Timer::start(LENGTHS, 0);
+        assert_eq!(t.advance(25_000), Some(Phase::Work));
+        assert_eq!((t.phase, t.round, t.run), (Phase::LongBreak, 2, Run::Running { ends: 27_000 }));
+    }
+
+    #[test]
+    fn paused_timer_stands_still() {
+        let mut t = Timer::start(LENGTHS, 0);
+        t.pause(4_000);
+        assert_eq!(t.advance(60_000), None);
+        assert_eq!(t.left(60_000), 6_000);
+        t.resume(60_000);
+        assert_eq!(t.run, Run::Running { ends: 66_000 });
+    }
+
+    #[test]
+    fn zero_length_phases_still_end() {
+        let mut t = Timer::start(Lengths { work: 0, short: 0, long: 0, rounds: 1 }, 0);
+        // each lasts a second: long break 0-1s, work -2s, long break -3s
+        assert_eq!(t.advance(2_500), Some(Phase::Work));
+        assert_eq!((t.phase, t.run), (Phase::LongBreak, Run::Running { ends: 3_000 }));
+    }
+
+    #[test]
+    fn encode_round_trips() {
+        let mut t = Timer::start(LENGTHS, 1_000);
+        assert_eq!(Timer::decode(&t.encode()), Some(t));
+        t.advance(11_000);
+        t.pause(11_500);
+        assert_eq!(Timer::decode(&t.encode()), Some(t));
+    }
+
+    #[test]
+    fn decode_needs_every_field() {
+        let text = Timer::start(LENGTHS, 0).encode();
+        for skip in 0..text.lines().count() {
+            let partial: String = text.lines().enumerate()
+                .filter(|&(i, _)| i != skip)
+                .map(|(_, l)| format!("{l}\n"))
+                .collect();
+            assert_eq!(Timer::decode(&partial), None, "without line {skip}");
+        }
+        assert_eq!(Timer::decode(&text.replace("phase work", "phase lunch")), None);
+    }
+}
@@ -0,0 +1,50 @@
+// Asking the tmux server we run under about its panes.
+
+use std::process::{Command, Stdio};
+
+/// Which pane's directory to ask for; with neither set tmux picks the
+/// active pane of the most recently used client.
+#[derive(Clone, Copy, Default)]
+pub struct Target<'a> {
+    /// A pane, e.g. %3 or work:1.2
+    pub pane: Option<&'a str>,
+    /// A client name (its tty), e.g. /dev/pts/4
+    pub client: Option<&'a str>,
+}
+
+/// The current directory of the target pane; None outside tmux or when the
+/// server doesn't answer.
+pub fn pane_path(target: Target) -> Option<String> {
+    std::env::var_os("TMUX")?;
+    let mut cmd = Command::new("tmux");
+    cmd.args(["display-message", "-p"]);
+    if let Some(c) = target.client {
+        cmd.args(["-c", c]);
+    }
+    if let Some(p) = target.pane {
+        cmd.args(["-t", p]);
+    }
+    let out = cmd.arg("#{pane_current_path}")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let path = String::from_utf8(out.stdout).ok()?;
+    let path = path.trim_end_matches('\n');
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Runs a tmux command against the running server; false when there is
+/// none or the command failed.
+pub fn run(args: &[&str]) -> bool {
+    Command::new("tmux")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}